@@ -0,0 +1,4 @@
+// makes `sqlx::migrate!` pick up new migrations without touching the sources
+fn main() {
+    println!("cargo:rerun-if-changed=migrations");
+}
@@ -1,22 +1,36 @@
-CREATE TYPE day_type AS ENUM (
-    'monday',
-    'tuesday',
-    'wednesday',
-    'thursday',
-    'friday',
-    'saturday',
-    'sunday'
-);
+-- The schema as it was before migrations were introduced.
+--
+-- Databases created from the old `schema.sql` already have these objects,
+-- so every statement is a no-op for them and the migration is just recorded.
+-- Everything added later lives in the following migrations.
+
+DO $$ BEGIN
+    CREATE TYPE day_type AS ENUM (
+        'monday',
+        'tuesday',
+        'wednesday',
+        'thursday',
+        'friday',
+        'saturday',
+        'sunday'
+    );
+EXCEPTION
+    WHEN duplicate_object THEN NULL;
+END $$;
 
-CREATE TYPE week_type AS ENUM ('odd', 'even');
+DO $$ BEGIN
+    CREATE TYPE week_type AS ENUM ('odd', 'even');
+EXCEPTION
+    WHEN duplicate_object THEN NULL;
+END $$;
 
-CREATE TABLE majors (
+CREATE TABLE IF NOT EXISTS majors (
     id text PRIMARY KEY,
     title text,
     enrollment_year smallint
 );
 
-CREATE TABLE timetable (
+CREATE TABLE IF NOT EXISTS timetable (
     id bigint GENERATED ALWAYS AS identity (minvalue 1000) PRIMARY KEY,
     major_id text,
     week week_type NOT NULL,
@@ -37,7 +51,7 @@ CREATE TABLE timetable (
                 ON DELETE SET NULL
 );
 
-CREATE TABLE users (
+CREATE TABLE IF NOT EXISTS users (
     id bigint PRIMARY KEY,
     major_id text,
     CONSTRAINT fk_major
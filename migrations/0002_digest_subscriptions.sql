@@ -0,0 +1,10 @@
+CREATE TABLE digest_subscriptions (
+    user_id bigint PRIMARY KEY,
+    send_at time NOT NULL,
+    next_day boolean NOT NULL,
+    last_sent_on date,
+    CONSTRAINT fk_user
+        FOREIGN KEY (user_id)
+            REFERENCES users(id)
+            ON DELETE CASCADE
+);
@@ -0,0 +1,21 @@
+CREATE TABLE reminder_settings (
+    user_id bigint PRIMARY KEY,
+    lead_minutes smallint NOT NULL CHECK (lead_minutes BETWEEN 1 AND 180),
+    CONSTRAINT fk_user
+        FOREIGN KEY (user_id)
+            REFERENCES users(id)
+            ON DELETE CASCADE
+);
+
+CREATE TABLE sent_reminders (
+    user_id bigint NOT NULL,
+    lesson_date date NOT NULL,
+    starts_at time NOT NULL,
+
+    PRIMARY KEY (user_id, lesson_date, starts_at),
+
+    CONSTRAINT fk_user
+        FOREIGN KEY (user_id)
+            REFERENCES users(id)
+            ON DELETE CASCADE
+);
@@ -0,0 +1,16 @@
+-- semesters without a major apply to every major which has no semesters of its own
+CREATE TABLE semesters (
+    id bigint GENERATED ALWAYS AS identity PRIMARY KEY,
+    major_id text,
+    starts_on date NOT NULL,
+    ends_on date NOT NULL,
+    first_week week_type NOT NULL DEFAULT 'odd',
+
+    CHECK (starts_on <= ends_on),
+
+    CONSTRAINT fk_major
+        FOREIGN KEY (major_id)
+            REFERENCES majors(id)
+            ON UPDATE CASCADE
+            ON DELETE CASCADE
+);
@@ -0,0 +1,35 @@
+CREATE TYPE override_kind AS ENUM ('cancel', 'move', 'extra', 'holiday');
+
+-- cancel and move refer to an occurrence of a timetable row on `date`,
+-- extra is a one-off lesson on `date`, holiday cancels every lesson on `date`
+CREATE TABLE timetable_overrides (
+    id bigint GENERATED ALWAYS AS identity PRIMARY KEY,
+    kind override_kind NOT NULL,
+    major_id text,
+    timetable_id bigint,
+    date date NOT NULL,
+    new_date date,
+    starts_at time,
+    subject_name text,
+    subject_type text,
+    auditorium text,
+    professor text,
+
+    CHECK (kind NOT IN ('cancel', 'move') OR timetable_id IS NOT NULL),
+    CHECK (kind <> 'move' OR new_date IS NOT NULL),
+    CHECK (
+        kind <> 'extra'
+        OR (major_id IS NOT NULL AND starts_at IS NOT NULL AND subject_name IS NOT NULL
+            AND subject_type IS NOT NULL AND auditorium IS NOT NULL)
+    ),
+
+    CONSTRAINT fk_major
+        FOREIGN KEY (major_id)
+            REFERENCES majors(id)
+            ON UPDATE CASCADE
+            ON DELETE CASCADE,
+    CONSTRAINT fk_timetable
+        FOREIGN KEY (timetable_id)
+            REFERENCES timetable(id)
+            ON DELETE CASCADE
+);
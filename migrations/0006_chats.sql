@@ -0,0 +1,10 @@
+-- default major of a group chat, used for members who have not set their own
+CREATE TABLE chats (
+    id bigint PRIMARY KEY,
+    major_id text NOT NULL,
+    CONSTRAINT fk_major
+        FOREIGN KEY (major_id)
+            REFERENCES majors(id)
+            ON UPDATE CASCADE
+            ON DELETE CASCADE
+);
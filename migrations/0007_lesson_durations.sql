@@ -0,0 +1,29 @@
+-- lessons used to last exactly 90 minutes, now every lesson stores its own duration
+ALTER TABLE timetable DROP COLUMN ends_at;
+ALTER TABLE timetable
+    ADD COLUMN duration_minutes smallint NOT NULL DEFAULT 90
+        CHECK (duration_minutes BETWEEN 1 AND 720);
+ALTER TABLE timetable
+    ADD COLUMN ends_at time
+        GENERATED ALWAYS AS (starts_at + duration_minutes * interval '1 minute') STORED;
+
+-- only one-off lessons have a duration
+ALTER TABLE timetable_overrides
+    ADD COLUMN duration_minutes smallint CHECK (duration_minutes BETWEEN 1 AND 720);
+
+-- default durations of new lessons, a missing major or subject type matches any of them
+CREATE TABLE lesson_durations (
+    id bigint GENERATED ALWAYS AS identity PRIMARY KEY,
+    major_id text,
+    subject_type text,
+    duration_minutes smallint NOT NULL CHECK (duration_minutes BETWEEN 1 AND 720),
+
+    CONSTRAINT fk_major
+        FOREIGN KEY (major_id)
+            REFERENCES majors(id)
+            ON UPDATE CASCADE
+            ON DELETE CASCADE
+);
+
+CREATE UNIQUE INDEX lesson_durations_scope
+    ON lesson_durations (COALESCE(major_id, ''), COALESCE(subject_type, ''));
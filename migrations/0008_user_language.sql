@@ -0,0 +1,3 @@
+CREATE TYPE language_code AS ENUM ('ru', 'en');
+
+ALTER TABLE users ADD COLUMN language language_code NOT NULL DEFAULT 'ru';
@@ -0,0 +1,49 @@
+-- parts of a major which have some lessons separately, e.g. lab halves or language groups
+CREATE TABLE subgroups (
+    major_id text NOT NULL,
+    name text NOT NULL CHECK (name <> ''),
+
+    PRIMARY KEY (major_id, name),
+
+    CONSTRAINT fk_major
+        FOREIGN KEY (major_id)
+            REFERENCES majors(id)
+            ON UPDATE CASCADE
+            ON DELETE CASCADE
+);
+
+-- lessons without a subgroup are for the whole major
+ALTER TABLE timetable ADD COLUMN subgroup text;
+
+ALTER TABLE timetable
+    ADD CONSTRAINT fk_subgroup
+        FOREIGN KEY (major_id, subgroup)
+            REFERENCES subgroups(major_id, name)
+            ON DELETE CASCADE;
+
+-- subgroups of a major may have different lessons at the same time,
+-- the name of the old constraint was picked by Postgres, so it is looked up by its columns
+DO $$
+DECLARE
+    slot_constraint name;
+BEGIN
+    SELECT con.conname INTO slot_constraint
+    FROM pg_constraint con
+    WHERE con.conrelid = 'timetable'::regclass
+        AND con.contype = 'u'
+        AND (
+            SELECT array_agg(att.attname::text ORDER BY att.attname)
+            FROM pg_attribute att
+            WHERE att.attrelid = con.conrelid AND att.attnum = ANY (con.conkey)
+        ) = ARRAY['day_of_week', 'major_id', 'starts_at', 'week'];
+
+    IF slot_constraint IS NOT NULL THEN
+        EXECUTE format('ALTER TABLE timetable DROP CONSTRAINT %I', slot_constraint);
+    END IF;
+END $$;
+
+CREATE UNIQUE INDEX timetable_slot
+    ON timetable (major_id, week, day_of_week, starts_at, COALESCE(subgroup, ''));
+
+-- users without a subgroup see the lessons of every subgroup of their major
+ALTER TABLE users ADD COLUMN subgroup text;
@@ -0,0 +1,19 @@
+-- subjects of other majors which a user attends on top of their own timetable
+CREATE TABLE electives (
+    user_id bigint NOT NULL,
+    major_id text NOT NULL,
+    subject_name text NOT NULL,
+
+    PRIMARY KEY (user_id, major_id, subject_name),
+
+    CONSTRAINT fk_user
+        FOREIGN KEY (user_id)
+            REFERENCES users(id)
+            ON DELETE CASCADE,
+
+    CONSTRAINT fk_major
+        FOREIGN KEY (major_id)
+            REFERENCES majors(id)
+            ON UPDATE CASCADE
+            ON DELETE CASCADE
+);
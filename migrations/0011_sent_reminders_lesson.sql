@@ -0,0 +1,6 @@
+-- an elective may start at the same time as a lesson of the user's own major,
+-- so the lesson is a part of the key, e.g. `timetable-1042` or `extra-7`
+ALTER TABLE sent_reminders ADD COLUMN lesson_id text NOT NULL DEFAULT '';
+
+ALTER TABLE sent_reminders DROP CONSTRAINT sent_reminders_pkey;
+ALTER TABLE sent_reminders ADD PRIMARY KEY (user_id, lesson_date, starts_at, lesson_id);
@@ -0,0 +1,10 @@
+-- lessons of a subgroup follow it like the other references to majors do:
+-- they are deleted together with the subgroup and renamed together with its major
+ALTER TABLE timetable DROP CONSTRAINT fk_subgroup;
+
+ALTER TABLE timetable
+    ADD CONSTRAINT fk_subgroup
+        FOREIGN KEY (major_id, subgroup)
+            REFERENCES subgroups(major_id, name)
+            ON UPDATE CASCADE
+            ON DELETE CASCADE;
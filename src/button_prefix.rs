@@ -4,5 +4,14 @@ use strum::EnumString;
 #[strum(serialize_all = "kebab-case")]
 pub enum ButtonPrefix {
     SetMajor,
-    TimetableWeekday
+    SetChatMajor,
+    SetSubgroup,
+    SetLanguage,
+    TimetableWeekday,
+    TimetableWeek,
+    ProfessorWeekday,
+    ElectiveMajor,
+    ToggleElective,
+    AdminConfirm,
+    AdminCancel,
 }
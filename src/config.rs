@@ -1,32 +1,127 @@
+use std::path::{Path, PathBuf};
+
+use chrono_tz::Tz;
 use serde::Deserialize;
 
 use figment::{
-    providers::{Format, Toml},
+    providers::{Env, Format, Toml},
     Figment,
 };
 
+/// Config file which is used if no `--config` is given, it may be missing.
+pub const DEFAULT_CONFIG_PATH: &str = "./config.toml";
+
+/// Prefix of environment variables which override the config file, nested keys are
+/// separated by `__`, e.g. `UNIBOT_TELEGRAM__TOKEN`.
+const ENV_PREFIX: &str = "UNIBOT_";
+
+#[derive(Debug, thiserror::Error)]
+pub enum ConfigError {
+    #[error("config file {} does not exist", .0.display())]
+    MissingFile(PathBuf),
+
+    #[error("invalid config:\n{}", format_problems(.0))]
+    Invalid(Vec<String>),
+}
+
+fn format_problems(problems: &[String]) -> String {
+    problems
+        .iter()
+        .map(|problem| format!("  - {problem}"))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+impl From<figment::Error> for ConfigError {
+    fn from(err: figment::Error) -> Self {
+        Self::Invalid(err.into_iter().map(|err| err.to_string()).collect())
+    }
+}
+
 #[derive(Debug, Deserialize)]
 pub struct Telegram {
     pub token: String,
     pub owner_ids: Vec<u64>,
 }
 
+impl Telegram {
+    pub fn is_owner(&self, user_id: u64) -> bool {
+        self.owner_ids.contains(&user_id)
+    }
+}
+
 #[derive(Debug, Deserialize)]
 pub struct Database {
     pub url: String,
 }
 
+#[derive(Debug, Deserialize)]
+pub struct Institution {
+    /// IANA name of the timezone lessons are scheduled in, e.g. `Europe/Samara`.
+    pub timezone: Tz,
+}
+
+impl Default for Institution {
+    fn default() -> Self {
+        Self {
+            timezone: crate::utils::time::DEFAULT_TIMEZONE,
+        }
+    }
+}
+
 #[derive(Debug, Deserialize)]
 pub struct AppConfig {
     pub telegram: Telegram,
     pub database: Database,
+    #[serde(default)]
+    pub institution: Institution,
 }
 
 impl AppConfig {
-    pub fn figment() -> AppConfig {
+    /// Sources of the config, environment variables take precedence over the file.
+    pub fn figment(path: &Path) -> Figment {
         Figment::new()
-            .merge(Toml::file("./config.toml"))
-            .extract()
-            .unwrap()
+            .merge(Toml::file(path))
+            .merge(Env::prefixed(ENV_PREFIX).split("__"))
+    }
+
+    /// Loads the config from `path`, or from [`DEFAULT_CONFIG_PATH`] if it is not given.
+    ///
+    /// An explicitly given file must exist, the default one may be replaced by
+    /// environment variables completely.
+    pub fn load(path: Option<&Path>) -> Result<AppConfig, ConfigError> {
+        if let Some(path) = path {
+            if !path.is_file() {
+                return Err(ConfigError::MissingFile(path.to_owned()));
+            }
+        }
+
+        let path = path.unwrap_or(Path::new(DEFAULT_CONFIG_PATH));
+        let config: AppConfig = Self::figment(path).extract()?;
+        config.validate()?;
+
+        Ok(config)
+    }
+
+    fn validate(&self) -> Result<(), ConfigError> {
+        let mut problems = vec![];
+
+        if self.telegram.token.trim().is_empty() {
+            problems.push("telegram.token must not be empty".to_owned());
+        }
+
+        if self.telegram.owner_ids.is_empty() {
+            problems.push("telegram.owner_ids must contain at least one user id".to_owned());
+        }
+
+        if self.database.url.trim().is_empty() {
+            problems.push("database.url must not be empty".to_owned());
+        }
+
+        if problems.is_empty() {
+            Ok(())
+        } else {
+            Err(ConfigError::Invalid(problems))
+        }
     }
 }
@@ -1,23 +1,522 @@
-use std::sync::Arc;
+use std::{str::FromStr, sync::Arc};
 
-use anyhow::Result;
+use anyhow::{anyhow, bail, Result};
+use chrono::{NaiveDate, NaiveTime};
 use rand::Rng;
-use teloxide::{prelude::*, utils::command::BotCommands};
+use teloxide::{
+    net::Download,
+    prelude::*,
+    types::{InlineKeyboardButton, InlineKeyboardMarkup, ParseMode},
+    utils::command::BotCommands,
+};
 
-use crate::{config::AppConfig, utils::database::Database};
+use crate::{
+    button_prefix::ButtonPrefix,
+    config::AppConfig,
+    tasks::notifier::Notifier,
+    utils::{
+        durations::LessonDurations,
+        i18n::{tr, tr_args, Language},
+        import::{check_duration, parse_document, ImportFormat, TimetableDiff, MAX_IMPORT_SIZE},
+        pending::Pending,
+        sql::types::{
+            DayOfWeek, LessonDurationEntry, NewTimeTableEntry, OverrideKind, SemesterEntry,
+            SubgroupEntry, TimeTableKey, WeekType, DEFAULT_LESSON_DURATION_MINUTES,
+        },
+        storage::Storage,
+    },
+};
+
+use super::{overrides, schedule::format_entry};
+
+/// Replies to the owners are in Russian, lessons in them are formatted in the same language.
+pub const OWNER_LANGUAGE: Language = Language::Ru;
 
 #[derive(BotCommands, Clone)]
-#[command(rename_rule = "lowercase", description = "Admin commands")]
+#[command(rename_rule = "lowercase", description = "Команды администратора")]
 pub enum AdminCommand {
-    #[command(description = "Help for admin only commands")]
+    #[command(description = "Список команд администратора")]
     AdminHelp,
-    #[command(parse_with = "split", description = "generate a number within range")]
-    Rand { from: u64, to: u64 }
+    #[command(parse_with = "split", description = "случайное число в диапазоне")]
+    Rand { from: u64, to: u64 },
+    #[command(
+        description = "добавить занятие: группа[/подгруппа]; неделя; день; ЧЧ:ММ; предмет; тип; аудитория[; преподаватель[; минуты]]"
+    )]
+    AddEntry(String),
+    #[command(
+        description = "изменить занятие: группа[/подгруппа]; неделя; день; ЧЧ:ММ; предмет; тип; аудитория[; преподаватель[; минуты]]"
+    )]
+    EditEntry(String),
+    #[command(description = "удалить занятие: группа[/подгруппа]; неделя; день; ЧЧ:ММ")]
+    DelEntry(String),
+    #[command(description = "как импортировать расписание семестра из документа")]
+    Import,
+    #[command(description = "список семестров")]
+    Semesters,
+    #[command(
+        description = "добавить семестр: группа или *; ГГГГ-ММ-ДД; ГГГГ-ММ-ДД[; первая неделя odd/even]"
+    )]
+    AddSemester(String),
+    #[command(description = "удалить семестр по id")]
+    DelSemester(i64),
+    #[command(description = "список длительностей занятий по умолчанию")]
+    Durations,
+    #[command(
+        description = "задать длительность занятий по умолчанию: группа или *; тип или *; минуты"
+    )]
+    SetDuration(String),
+    #[command(description = "удалить длительность по id")]
+    DelDuration(i64),
+    #[command(description = "список предстоящих изменений расписания")]
+    Overrides,
+    #[command(description = "объявить выходной: ГГГГ-ММ-ДД[; группа]")]
+    Holiday(String),
+    #[command(description = "отменить занятие: группа[/подгруппа]; ГГГГ-ММ-ДД; ЧЧ:ММ")]
+    CancelLesson(String),
+    #[command(
+        description = "перенести занятие: группа[/подгруппа]; ГГГГ-ММ-ДД; ЧЧ:ММ; новая ГГГГ-ММ-ДД[; новое ЧЧ:ММ[; аудитория]]"
+    )]
+    MoveLesson(String),
+    #[command(
+        description = "добавить разовое занятие: группа; ГГГГ-ММ-ДД; ЧЧ:ММ; предмет; тип; аудитория[; преподаватель]"
+    )]
+    ExtraLesson(String),
+    #[command(description = "удалить изменение расписания по id")]
+    DelOverride(i64),
+    #[command(description = "список подгрупп[: группа]")]
+    Subgroups(String),
+    #[command(description = "добавить подгруппу: группа; название")]
+    AddSubgroup(String),
+    #[command(description = "удалить подгруппу вместе с её занятиями: группа; название")]
+    DelSubgroup(String),
+}
+
+const IMPORT_HELP: &str = r#"Отправьте документ .json или .csv, указав в подписи id группы.
+
+Документ заменяет всё расписание группы. Каждая строка содержит поля week (odd/even), day_of_week (monday…sunday), starts_at (HH:MM), subject_name, subject_type, auditorium и необязательные professor, duration_minutes и subgroup.
+
+JSON: [{"week": "odd", "day_of_week": "monday", "starts_at": "08:00", "subject_name": "…", "subject_type": "…", "auditorium": "…", "professor": "…", "duration_minutes": 90, "subgroup": "1"}]
+
+CSV: первая строка – заголовок с названиями полей."#;
+
+/// How many lines of each section are shown in the import preview.
+const DIFF_PREVIEW_LINES: usize = 20;
+
+/// A change of the `timetable` table which waits for the owner's confirmation.
+#[derive(Debug, Clone)]
+pub enum PendingChange {
+    Insert(NewTimeTableEntry),
+    Update(NewTimeTableEntry),
+    Delete(TimeTableKey),
+    Import {
+        major_id: String,
+        entries: Vec<NewTimeTableEntry>,
+    },
+}
+
+pub(super) fn split_args(args: &str) -> Vec<&str> {
+    args.split(';').map(str::trim).collect()
+}
+
+/// Splits `major/subgroup` into the major id and the subgroup, which is optional.
+pub(super) fn parse_major(value: &str) -> Result<(String, Option<String>)> {
+    let (major_id, subgroup) = match value.split_once('/') {
+        Some((major_id, subgroup)) => (major_id.trim(), Some(subgroup.trim())),
+        None => (value.trim(), None),
+    };
+
+    if major_id.is_empty() {
+        bail!("id группы не может быть пустым");
+    }
+
+    if subgroup == Some("") {
+        bail!("подгруппа не может быть пустой, для всей группы укажите id без /подгруппы");
+    }
+
+    Ok((major_id.to_owned(), subgroup.map(str::to_owned)))
+}
+
+fn parse_key(args: &[&str]) -> Result<TimeTableKey> {
+    let [major_id, week, day_of_week, starts_at, ..] = args else {
+        bail!("ожидалось не меньше 4 аргументов, получено {}", args.len())
+    };
+
+    let (major_id, subgroup) = parse_major(major_id)?;
+
+    let week = WeekType::from_str(week).map_err(|_| anyhow!("неизвестный тип недели {week:?}"))?;
+    let day_of_week = DayOfWeek::from_str(day_of_week)
+        .map_err(|_| anyhow!("неизвестный день {day_of_week:?}"))?;
+    let starts_at = NaiveTime::parse_from_str(starts_at, "%H:%M")
+        .map_err(|_| anyhow!("неверное время {starts_at:?}, ожидалось ЧЧ:ММ"))?;
+
+    Ok(TimeTableKey {
+        major_id,
+        subgroup,
+        week,
+        day_of_week,
+        starts_at,
+    })
+}
+
+fn parse_entry(args: &str, durations: &LessonDurations) -> Result<NewTimeTableEntry> {
+    let args = split_args(args);
+    let key = parse_key(&args)?;
+
+    let (subject_name, subject_type, auditorium, professor, minutes) = match args[4..] {
+        [name, kind, auditorium] => (name, kind, auditorium, None, None),
+        [name, kind, auditorium, professor] => (name, kind, auditorium, Some(professor), None),
+        [name, kind, auditorium, professor, minutes] => {
+            (name, kind, auditorium, Some(professor), Some(minutes))
+        }
+        _ => bail!("ожидалось от 7 до 9 аргументов, получено {}", args.len()),
+    };
+
+    if subject_name.is_empty() || subject_type.is_empty() || auditorium.is_empty() {
+        bail!("предмет, тип и аудитория не могут быть пустыми");
+    }
+
+    let duration_minutes =
+        match minutes {
+            Some(minutes) => check_duration(minutes.parse().map_err(|_| {
+                anyhow!("неверная длительность {minutes:?}, ожидалось число минут")
+            })?)?,
+            None => durations.minutes_for(&key.major_id, subject_type),
+        };
+
+    Ok(NewTimeTableEntry {
+        key,
+        subject_name: subject_name.to_string(),
+        subject_type: subject_type.to_string(),
+        auditorium: auditorium.to_string(),
+        professor: professor
+            .filter(|value| !value.is_empty())
+            .map(str::to_string),
+        duration_minutes,
+    })
+}
+
+fn format_key(key: &TimeTableKey) -> String {
+    let s = format!(
+        "{} {} {}",
+        key.week,
+        key.day_of_week,
+        key.starts_at.format("%H:%M")
+    );
+
+    match key.subgroup.as_ref() {
+        Some(subgroup) => format!("{s} [{subgroup}]"),
+        None => s,
+    }
+}
+
+fn format_diff_section<T>(
+    title: &str,
+    items: &[T],
+    format_item: impl Fn(&T) -> String,
+) -> Option<String> {
+    if items.is_empty() {
+        return None;
+    }
+
+    let mut s = format!("<b>{title} ({})</b>", items.len());
+    for item in items.iter().take(DIFF_PREVIEW_LINES) {
+        s = format!("{s}\n{}", format_item(item));
+    }
+
+    if items.len() > DIFF_PREVIEW_LINES {
+        s = format!("{s}\n… и ещё {}", items.len() - DIFF_PREVIEW_LINES);
+    }
+
+    Some(s)
+}
+
+fn format_diff(major_id: &str, diff: &TimetableDiff) -> String {
+    let sections = [
+        format_diff_section("Будут добавлены", &diff.added, |entry| {
+            format!(
+                "+ {} {} ({})",
+                format_key(&entry.key),
+                entry.subject_name,
+                entry.auditorium
+            )
+        }),
+        format_diff_section(
+            "Будут изменены",
+            &diff.changed,
+            |(old, new)| {
+                format!(
+                    "~ {} {} ({}, {} мин) → {} ({}, {} мин)",
+                    format_key(&new.key),
+                    old.subject_name,
+                    old.auditorium,
+                    old.duration_minutes,
+                    new.subject_name,
+                    new.auditorium,
+                    new.duration_minutes
+                )
+            },
+        ),
+        format_diff_section("Будут удалены", &diff.removed, |entry| {
+            format!(
+                "− {} {} ({})",
+                format_key(&entry.key()),
+                entry.subject_name,
+                entry.auditorium
+            )
+        }),
+    ];
+
+    let mut s = format!("<b>Импорт расписания группы {major_id}</b>");
+    for section in sections.into_iter().flatten() {
+        s = format!("{s}\n\n{section}");
+    }
+
+    format!("{s}\n\nБез изменений: {}", diff.unchanged)
+}
+
+fn parse_semester(args: &str) -> Result<(Option<String>, NaiveDate, NaiveDate, WeekType)> {
+    let args = split_args(args);
+
+    let (major_id, starts_on, ends_on, first_week) = match args[..] {
+        [major_id, starts_on, ends_on] => (major_id, starts_on, ends_on, "odd"),
+        [major_id, starts_on, ends_on, first_week] => (major_id, starts_on, ends_on, first_week),
+        _ => bail!("ожидалось 3 или 4 аргумента, получено {}", args.len()),
+    };
+
+    let major_id = match major_id {
+        "" => bail!("id группы не может быть пустым, укажите * для всех групп"),
+        "*" => None,
+        value => Some(value.to_owned()),
+    };
+
+    let starts_on = NaiveDate::parse_from_str(starts_on, "%Y-%m-%d")
+        .map_err(|_| anyhow!("неверная дата {starts_on:?}, ожидалось ГГГГ-ММ-ДД"))?;
+    let ends_on = NaiveDate::parse_from_str(ends_on, "%Y-%m-%d")
+        .map_err(|_| anyhow!("неверная дата {ends_on:?}, ожидалось ГГГГ-ММ-ДД"))?;
+
+    if ends_on < starts_on {
+        bail!("семестр заканчивается раньше, чем начинается");
+    }
+
+    let first_week = WeekType::from_str(first_week)
+        .map_err(|_| anyhow!("неизвестный тип недели {first_week:?}"))?;
+
+    Ok((major_id, starts_on, ends_on, first_week))
+}
+
+fn format_semester(semester: &SemesterEntry) -> String {
+    format!(
+        "#{} {}: {} – {}, первая неделя {}",
+        semester.id,
+        semester.major_id.as_deref().unwrap_or("*"),
+        semester.starts_on,
+        semester.ends_on,
+        semester.first_week
+    )
+}
+
+fn parse_duration(args: &str) -> Result<(Option<String>, Option<String>, i16)> {
+    let args = split_args(args);
+
+    let [major_id, subject_type, minutes] = args[..] else {
+        bail!("ожидалось 3 аргумента, получено {}", args.len())
+    };
+
+    let scope = |value: &str| match value {
+        "" => bail!("укажите * вместо пустого значения"),
+        "*" => Ok(None),
+        value => Ok(Some(value.to_owned())),
+    };
+
+    let minutes = minutes
+        .parse()
+        .map_err(|_| anyhow!("неверная длительность {minutes:?}, ожидалось число минут"))?;
+
+    Ok((
+        scope(major_id)?,
+        scope(subject_type)?,
+        check_duration(minutes)?,
+    ))
+}
+
+fn format_duration(entry: &LessonDurationEntry) -> String {
+    format!(
+        "#{} {} {}: {} min",
+        entry.id,
+        entry.major_id.as_deref().unwrap_or("*"),
+        entry.subject_type.as_deref().unwrap_or("*"),
+        entry.duration_minutes
+    )
+}
+
+fn make_confirm_keyboard(id: u64) -> InlineKeyboardMarkup {
+    let confirm = format!("{}:{id}", ButtonPrefix::AdminConfirm);
+    let cancel = format!("{}:{id}", ButtonPrefix::AdminCancel);
+
+    InlineKeyboardMarkup::new(vec![vec![
+        InlineKeyboardButton::callback("Подтвердить", confirm),
+        InlineKeyboardButton::callback("Отмена", cancel),
+    ]])
+}
+
+fn parse_subgroup(args: &str) -> Result<(String, String)> {
+    let args = split_args(args);
+
+    let [major_id, name] = args[..] else {
+        bail!("ожидалось 2 аргумента, получено {}", args.len())
+    };
+
+    if major_id.is_empty() || name.is_empty() {
+        bail!("id группы и название подгруппы не могут быть пустыми");
+    }
+
+    if name.contains('/') {
+        bail!("название подгруппы не может содержать /");
+    }
+
+    Ok((major_id.to_owned(), name.to_owned()))
+}
+
+fn format_subgroup(subgroup: &SubgroupEntry) -> String {
+    format!("{}/{}", subgroup.major_id, subgroup.name)
+}
+
+/// The reason why lessons of the major and its subgroups can't be saved,
+/// if the major or some of the subgroups doesn't exist.
+async fn check_major<'a>(
+    storage: &dyn Storage,
+    major_id: &str,
+    subgroups: impl IntoIterator<Item = &'a String>,
+) -> Result<Option<String>> {
+    if storage.get_major(major_id).await?.is_none() {
+        return Ok(Some(format!("Группа {major_id} не найдена.")));
+    }
+
+    let existing = storage.get_subgroups(major_id).await?;
+
+    let missing = subgroups
+        .into_iter()
+        .find(|subgroup| !existing.iter().any(|entry| &entry.name == *subgroup));
+
+    Ok(missing.map(|subgroup| {
+        format!(
+            "Подгруппа {subgroup} не найдена у группы {major_id}, добавьте её через /addsubgroup."
+        )
+    }))
+}
+
+/// Renders the change which is about to be applied, or the reason why it can't be applied.
+async fn describe_change(
+    storage: &dyn Storage,
+    change: &PendingChange,
+) -> Result<Result<String, String>> {
+    let text = match change {
+        PendingChange::Insert(entry) => {
+            if let Some(reason) =
+                check_major(storage, &entry.key.major_id, &entry.key.subgroup).await?
+            {
+                return Ok(Err(reason));
+            }
+
+            if let Some(existing) = storage.get_timetable_entry_by_key(&entry.key).await? {
+                return Ok(Err(format!(
+                    "Занятие в это время уже существует:\n\n{}",
+                    format_entry(&existing, OWNER_LANGUAGE)?
+                )));
+            }
+
+            format!(
+                "<b>Добавить занятие?</b>\n\n{}",
+                format_entry(&entry.preview(), OWNER_LANGUAGE)?
+            )
+        }
+
+        PendingChange::Update(entry) => {
+            let Some(existing) = storage.get_timetable_entry_by_key(&entry.key).await? else {
+                return Ok(Err("Такое занятие не найдено.".to_owned()));
+            };
+
+            format!(
+                "<b>Изменить занятие?</b>\n\n<i>Было:</i>\n{}\n\n<i>Станет:</i>\n{}",
+                format_entry(&existing, OWNER_LANGUAGE)?,
+                format_entry(&entry.preview(), OWNER_LANGUAGE)?
+            )
+        }
+
+        PendingChange::Delete(key) => {
+            let Some(existing) = storage.get_timetable_entry_by_key(key).await? else {
+                return Ok(Err("Такое занятие не найдено.".to_owned()));
+            };
+
+            format!(
+                "<b>Удалить занятие?</b>\n\n{}",
+                format_entry(&existing, OWNER_LANGUAGE)?
+            )
+        }
+
+        PendingChange::Import { major_id, entries } => {
+            let subgroups = entries
+                .iter()
+                .filter_map(|entry| entry.key.subgroup.as_ref());
+            if let Some(reason) = check_major(storage, major_id, subgroups).await? {
+                return Ok(Err(reason));
+            }
+
+            let existing = storage.get_timetable_by_major(major_id).await?;
+            let diff = TimetableDiff::new(existing, entries.clone());
+
+            if diff.is_empty() {
+                return Ok(Err("Расписание не изменилось.".to_owned()));
+            }
+
+            format_diff(major_id, &diff)
+        }
+    };
+
+    Ok(Ok(text))
+}
+
+async fn request_confirmation(
+    storage: &dyn Storage,
+    pending: &Pending<PendingChange>,
+    bot: &Bot,
+    msg: &Message,
+    change: Result<PendingChange>,
+) -> Result<()> {
+    let change = match change {
+        Ok(change) => change,
+        Err(err) => {
+            bot.send_message(msg.chat.id, format!("Не удалось разобрать данные: {err}"))
+                .await?;
+            return Ok(());
+        }
+    };
+
+    let text = match describe_change(storage, &change).await? {
+        Ok(text) => text,
+        Err(reason) => {
+            bot.send_message(msg.chat.id, reason)
+                .parse_mode(ParseMode::Html)
+                .await?;
+            return Ok(());
+        }
+    };
+
+    let id = pending.insert(change);
+
+    bot.send_message(msg.chat.id, text)
+        .parse_mode(ParseMode::Html)
+        .reply_markup(make_confirm_keyboard(id))
+        .await?;
+
+    Ok(())
 }
 
 pub async fn commands_handler(
     _cfg: Arc<AppConfig>,
-    _db: Database,
+    storage: Arc<dyn Storage>,
+    notifier: Notifier,
+    pending: Pending<PendingChange>,
     bot: Bot,
     msg: Message,
     cmd: AdminCommand,
@@ -29,12 +528,513 @@ pub async fn commands_handler(
         }
 
         AdminCommand::Rand { from, to } => {
-            let mut rng = rand::rngs::OsRng::default();
+            let mut rng = rand::rngs::OsRng;
             let num = rng.gen_range(from..=to);
 
             bot.send_message(msg.chat.id, format!("{num}")).await?;
         }
+
+        AdminCommand::AddEntry(args) => {
+            let durations = LessonDurations::load(storage.as_ref()).await?;
+            let change = parse_entry(&args, &durations).map(PendingChange::Insert);
+            request_confirmation(storage.as_ref(), &pending, &bot, &msg, change).await?;
+        }
+
+        AdminCommand::EditEntry(args) => {
+            let durations = LessonDurations::load(storage.as_ref()).await?;
+            let change = parse_entry(&args, &durations).map(PendingChange::Update);
+            request_confirmation(storage.as_ref(), &pending, &bot, &msg, change).await?;
+        }
+
+        AdminCommand::DelEntry(args) => {
+            let change = parse_key(&split_args(&args)).map(PendingChange::Delete);
+            request_confirmation(storage.as_ref(), &pending, &bot, &msg, change).await?;
+        }
+
+        AdminCommand::Import => {
+            bot.send_message(msg.chat.id, IMPORT_HELP).await?;
+        }
+
+        AdminCommand::Semesters => {
+            let semesters = storage.get_all_semesters().await?;
+
+            let text = if semesters.is_empty() {
+                "Семестры не заданы, чётность недель определяется по номеру недели ISO.".to_owned()
+            } else {
+                semesters
+                    .iter()
+                    .map(format_semester)
+                    .collect::<Vec<_>>()
+                    .join("\n")
+            };
+
+            bot.send_message(msg.chat.id, text).await?;
+        }
+
+        AdminCommand::AddSemester(args) => {
+            let text = match parse_semester(&args) {
+                Ok((major_id, starts_on, ends_on, first_week)) => {
+                    let semester = storage
+                        .insert_semester(major_id.as_deref(), starts_on, ends_on, first_week)
+                        .await?;
+
+                    format!("Семестр добавлен: {}", format_semester(&semester))
+                }
+                Err(err) => format!("Не удалось разобрать данные: {err}"),
+            };
+
+            bot.send_message(msg.chat.id, text).await?;
+        }
+
+        AdminCommand::DelSemester(id) => {
+            let text = if storage.delete_semester(id).await? {
+                "Семестр удалён."
+            } else {
+                "Такой семестр не найден."
+            };
+
+            bot.send_message(msg.chat.id, text).await?;
+        }
+
+        AdminCommand::Durations => {
+            let durations = storage.get_lesson_durations().await?;
+
+            let text = if durations.is_empty() {
+                format!(
+                    "Длительности не заданы, занятия длятся {DEFAULT_LESSON_DURATION_MINUTES} минут."
+                )
+            } else {
+                durations
+                    .iter()
+                    .map(format_duration)
+                    .collect::<Vec<_>>()
+                    .join("\n")
+            };
+
+            bot.send_message(msg.chat.id, text).await?;
+        }
+
+        AdminCommand::SetDuration(args) => {
+            let text = match parse_duration(&args) {
+                Ok((major_id, subject_type, minutes)) => {
+                    let entry = storage
+                        .upsert_lesson_duration(
+                            major_id.as_deref(),
+                            subject_type.as_deref(),
+                            minutes,
+                        )
+                        .await?;
+
+                    format!(
+                        "Длительность сохранена: {}\nОна применяется к занятиям, которые будут добавлены после этого.",
+                        format_duration(&entry)
+                    )
+                }
+                Err(err) => format!("Не удалось разобрать данные: {err}"),
+            };
+
+            bot.send_message(msg.chat.id, text).await?;
+        }
+
+        AdminCommand::DelDuration(id) => {
+            let text = if storage.delete_lesson_duration(id).await? {
+                "Длительность удалена."
+            } else {
+                "Такая длительность не найдена."
+            };
+
+            bot.send_message(msg.chat.id, text).await?;
+        }
+
+        AdminCommand::Overrides => {
+            overrides::list_overrides_command_handler(storage.as_ref(), &bot, &msg).await?;
+        }
+
+        AdminCommand::Holiday(args) => {
+            overrides::add_override_command_handler(
+                storage.as_ref(),
+                &notifier,
+                &bot,
+                &msg,
+                OverrideKind::Holiday,
+                &args,
+            )
+            .await?;
+        }
+
+        AdminCommand::CancelLesson(args) => {
+            overrides::add_override_command_handler(
+                storage.as_ref(),
+                &notifier,
+                &bot,
+                &msg,
+                OverrideKind::Cancel,
+                &args,
+            )
+            .await?;
+        }
+
+        AdminCommand::MoveLesson(args) => {
+            overrides::add_override_command_handler(
+                storage.as_ref(),
+                &notifier,
+                &bot,
+                &msg,
+                OverrideKind::Move,
+                &args,
+            )
+            .await?;
+        }
+
+        AdminCommand::ExtraLesson(args) => {
+            overrides::add_override_command_handler(
+                storage.as_ref(),
+                &notifier,
+                &bot,
+                &msg,
+                OverrideKind::Extra,
+                &args,
+            )
+            .await?;
+        }
+
+        AdminCommand::DelOverride(id) => {
+            overrides::delete_override_command_handler(storage.as_ref(), &notifier, &bot, &msg, id)
+                .await?;
+        }
+
+        AdminCommand::Subgroups(major_id) => {
+            let major_id = major_id.trim();
+            let subgroups = if major_id.is_empty() {
+                storage.get_all_subgroups().await?
+            } else {
+                storage.get_subgroups(major_id).await?
+            };
+
+            let text = if subgroups.is_empty() {
+                "Подгруппы не заданы.".to_owned()
+            } else {
+                subgroups
+                    .iter()
+                    .map(format_subgroup)
+                    .collect::<Vec<_>>()
+                    .join("\n")
+            };
+
+            bot.send_message(msg.chat.id, text).await?;
+        }
+
+        AdminCommand::AddSubgroup(args) => {
+            let text = match parse_subgroup(&args) {
+                Ok((major_id, name)) => {
+                    if storage.get_major(&major_id).await?.is_none() {
+                        format!("Группа {major_id} не найдена.")
+                    } else if storage.insert_subgroup(&major_id, &name).await? {
+                        format!("Подгруппа {major_id}/{name} добавлена.")
+                    } else {
+                        format!("Подгруппа {major_id}/{name} уже существует.")
+                    }
+                }
+                Err(err) => format!("Не удалось разобрать данные: {err}"),
+            };
+
+            bot.send_message(msg.chat.id, text).await?;
+        }
+
+        AdminCommand::DelSubgroup(args) => {
+            let text = match parse_subgroup(&args) {
+                Ok((major_id, name)) => {
+                    if storage.delete_subgroup(&major_id, &name).await? {
+                        format!("Подгруппа {major_id}/{name} удалена вместе с её занятиями.")
+                    } else {
+                        "Такая подгруппа не найдена.".to_owned()
+                    }
+                }
+                Err(err) => format!("Не удалось разобрать данные: {err}"),
+            };
+
+            bot.send_message(msg.chat.id, text).await?;
+        }
     }
 
     Ok(())
 }
+
+/// Applies the change and notifies the students of the affected major about it.
+async fn apply_change(
+    storage: &dyn Storage,
+    notifier: &Notifier,
+    change: PendingChange,
+) -> Result<String> {
+    let text = match change {
+        PendingChange::Insert(entry) => {
+            let entry = storage.insert_timetable_entry(&entry).await?;
+            let formatted = format_entry(&entry, OWNER_LANGUAGE)?;
+            let major_id = entry.key().major_id;
+
+            notifier.notify(Some(&major_id), move |lang| {
+                Ok(format!(
+                    "{}\n\n{}",
+                    tr(lang, "notice-lesson-added"),
+                    format_entry(&entry, lang)?
+                ))
+            });
+
+            format!("<b>Занятие добавлено</b>\n\n{formatted}")
+        }
+
+        PendingChange::Update(entry) => match storage.update_timetable_entry(&entry).await? {
+            Some((old, new)) => {
+                let formatted = format_entry(&new, OWNER_LANGUAGE)?;
+
+                notifier.notify(Some(&entry.key.major_id), move |lang| {
+                    Ok(format!(
+                        "{}\n\n<i>{}</i>\n{}\n\n<i>{}</i>\n{}",
+                        tr(lang, "notice-lesson-changed"),
+                        tr(lang, "notice-before"),
+                        format_entry(&old, lang)?,
+                        tr(lang, "notice-after"),
+                        format_entry(&new, lang)?
+                    ))
+                });
+
+                format!("<b>Занятие изменено</b>\n\n{formatted}")
+            }
+            None => "Такое занятие не найдено.".to_owned(),
+        },
+
+        PendingChange::Delete(key) => match storage.delete_timetable_entry(&key).await? {
+            Some(entry) => {
+                let formatted = format_entry(&entry, OWNER_LANGUAGE)?;
+
+                notifier.notify(Some(&key.major_id), move |lang| {
+                    Ok(format!(
+                        "{}\n\n{}",
+                        tr(lang, "notice-lesson-deleted"),
+                        format_entry(&entry, lang)?
+                    ))
+                });
+
+                format!("<b>Занятие удалено</b>\n\n{formatted}")
+            }
+            None => "Такое занятие не найдено.".to_owned(),
+        },
+
+        PendingChange::Import { major_id, entries } => {
+            // the diff is computed again, since the table might have changed after the preview
+            let diff = storage.import_timetable(&major_id, entries).await?;
+            let (added, changed, removed) = (
+                diff.added.len().to_string(),
+                diff.changed.len().to_string(),
+                diff.removed.len().to_string(),
+            );
+
+            let text = format!(
+                "<b>Расписание группы {major_id} импортировано</b>\n\nДобавлено: {added}\nИзменено: {changed}\nУдалено: {removed}"
+            );
+
+            if !diff.is_empty() {
+                notifier.notify(Some(&major_id), move |lang| {
+                    let args = [
+                        ("added", added.as_str()),
+                        ("changed", changed.as_str()),
+                        ("removed", removed.as_str()),
+                    ];
+                    Ok(tr_args(lang, "notice-timetable-imported", &args))
+                });
+            }
+
+            text
+        }
+    };
+
+    Ok(text)
+}
+
+/// Parses a timetable document sent by an owner and asks to confirm the import.
+pub async fn import_document_handler(
+    storage: Arc<dyn Storage>,
+    pending: Pending<PendingChange>,
+    bot: Bot,
+    msg: Message,
+) -> Result<()> {
+    let Some(document) = msg.document() else {
+        return Ok(());
+    };
+
+    let major_id = msg
+        .caption()
+        .unwrap_or_default()
+        .trim_start_matches("/import")
+        .trim();
+
+    if major_id.is_empty() {
+        bot.send_message(msg.chat.id, IMPORT_HELP).await?;
+        return Ok(());
+    }
+
+    if storage.get_major(major_id).await?.is_none() {
+        bot.send_message(msg.chat.id, format!("Группа {major_id} не найдена."))
+            .await?;
+        return Ok(());
+    }
+
+    let format = document
+        .file_name
+        .as_deref()
+        .and_then(ImportFormat::from_file_name);
+
+    let Some(format) = format else {
+        bot.send_message(msg.chat.id, "Поддерживаются только документы .json и .csv.")
+            .await?;
+        return Ok(());
+    };
+
+    if document.file.size > MAX_IMPORT_SIZE {
+        bot.send_message(msg.chat.id, "Документ слишком большой.")
+            .await?;
+        return Ok(());
+    }
+
+    let file = bot.get_file(&document.file.id).await?;
+    let mut data = Vec::with_capacity(file.size as usize);
+    bot.download_file(&file.path, &mut data).await?;
+
+    let durations = LessonDurations::load(storage.as_ref()).await?;
+    let change =
+        parse_document(format, &data, major_id, &durations).map(|entries| PendingChange::Import {
+            major_id: major_id.to_owned(),
+            entries,
+        });
+
+    request_confirmation(storage.as_ref(), &pending, &bot, &msg, change).await
+}
+
+pub async fn change_callback_handler(
+    storage: Arc<dyn Storage>,
+    notifier: Notifier,
+    pending: Pending<PendingChange>,
+    bot: Bot,
+    q: CallbackQuery,
+) -> Result<()> {
+    let Some(data) = q.data else { return Ok(()) };
+
+    let Some((button_prefix, id)) = data.split_once(':') else {
+        return Ok(());
+    };
+
+    let Ok(button_prefix) = ButtonPrefix::from_str(button_prefix) else {
+        return Ok(());
+    };
+
+    bot.answer_callback_query(q.id).await?;
+
+    let change = pending.take(id.parse()?);
+
+    let text = match (button_prefix, change) {
+        (_, None) => "Действие устарело, повторите команду.".to_owned(),
+        (ButtonPrefix::AdminConfirm, Some(change)) => {
+            apply_change(storage.as_ref(), &notifier, change).await?
+        }
+        (_, Some(_)) => "Действие отменено.".to_owned(),
+    };
+
+    if let Some(Message { id, chat, .. }) = q.message {
+        bot.edit_message_text(chat.id, id, text)
+            .parse_mode(ParseMode::Html)
+            .await?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::{
+        sql::types::MajorEntry,
+        storage::{fixtures::entry, memory::MemoryStorage},
+    };
+
+    #[tokio::test]
+    async fn import_replaces_the_timetable_and_notifies_the_major() {
+        let storage = MemoryStorage::default();
+        let (notifier, mut notices) = Notifier::new();
+
+        for change in [
+            PendingChange::Insert(entry(DayOfWeek::Monday, 9, "Физика")),
+            PendingChange::Insert(entry(DayOfWeek::Tuesday, 11, "Химия")),
+        ] {
+            apply_change(&storage, &notifier, change).await.unwrap();
+        }
+
+        let change = PendingChange::Import {
+            major_id: "ivt".to_owned(),
+            entries: vec![
+                NewTimeTableEntry {
+                    auditorium: "302".to_owned(),
+                    ..entry(DayOfWeek::Monday, 9, "Физика")
+                },
+                entry(DayOfWeek::Friday, 13, "История"),
+            ],
+        };
+        let text = apply_change(&storage, &notifier, change).await.unwrap();
+        assert!(text.contains("Добавлено: 1\nИзменено: 1\nУдалено: 1"));
+
+        let timetable = storage.get_timetable_by_major("ivt").await.unwrap();
+        let lessons = timetable
+            .iter()
+            .map(|entry| (entry.subject_name.as_str(), entry.auditorium.as_str()))
+            .collect::<Vec<_>>();
+        assert_eq!(lessons, [("Физика", "302"), ("История", "301")]);
+
+        let notices = std::iter::from_fn(|| notices.try_recv().ok()).collect::<Vec<_>>();
+        assert_eq!(notices.len(), 3);
+        assert!(notices
+            .iter()
+            .all(|notice| notice.major_id.as_deref() == Some("ivt")));
+        assert!(notices[2]
+            .render(Language::Ru)
+            .unwrap()
+            .contains("добавлено занятий – 1"));
+        assert!(notices[2].render(Language::En).unwrap().contains("1 added"));
+    }
+
+    #[tokio::test]
+    async fn lessons_of_a_missing_major_are_not_confirmed() {
+        let storage = MemoryStorage::default();
+
+        for change in [
+            PendingChange::Insert(entry(DayOfWeek::Monday, 9, "Физика")),
+            PendingChange::Import {
+                major_id: "ivt".to_owned(),
+                entries: vec![entry(DayOfWeek::Monday, 9, "Физика")],
+            },
+        ] {
+            let reason = describe_change(&storage, &change).await.unwrap();
+            assert_eq!(reason.unwrap_err(), "Группа ivt не найдена.");
+        }
+
+        storage.state().majors.push(MajorEntry {
+            id: "ivt".to_owned(),
+            title: "Информатика и вычислительная техника".to_owned(),
+            enrollment_year: 2023,
+        });
+
+        let change = PendingChange::Insert(entry(DayOfWeek::Monday, 9, "Физика"));
+        let text = describe_change(&storage, &change).await.unwrap();
+        assert!(text.unwrap().starts_with("<b>Добавить занятие?</b>"));
+    }
+
+    #[tokio::test]
+    async fn missing_lesson_is_not_updated() {
+        let storage = MemoryStorage::default();
+        let (notifier, mut notices) = Notifier::new();
+
+        let change = PendingChange::Update(entry(DayOfWeek::Monday, 9, "Физика"));
+        let text = apply_change(&storage, &notifier, change).await.unwrap();
+
+        assert_eq!(text, "Такое занятие не найдено.");
+        assert!(notices.try_recv().is_err());
+    }
+}
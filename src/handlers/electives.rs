@@ -0,0 +1,184 @@
+use std::sync::Arc;
+
+use anyhow::Result;
+use teloxide::{
+    prelude::*,
+    types::{InlineKeyboardButton, InlineKeyboardMarkup, ParseMode},
+};
+
+use crate::{
+    button_prefix::ButtonPrefix,
+    utils::{
+        i18n::{tr, tr_args, Language},
+        sql::types::UserEntry,
+        storage::Storage,
+    },
+};
+
+use super::{error::BotError, general::make_majors_keyboard};
+
+pub async fn electives_command_handler(
+    storage: &dyn Storage,
+    bot: &Bot,
+    msg: &Message,
+    user_entry: &UserEntry,
+    lang: Language,
+) -> Result<()> {
+    let electives = storage.get_electives(user_entry.id).await?;
+
+    let mut text = tr(lang, "choose-elective-major").to_owned();
+    if !electives.is_empty() {
+        text = format!("{text}\n\n<b>{}</b>", tr(lang, "electives-current"));
+
+        for elective in electives {
+            text = format!("{text}\n{} ({})", elective.subject_name, elective.major_id);
+        }
+    }
+
+    let kb = make_majors_keyboard(storage, ButtonPrefix::ElectiveMajor).await?;
+
+    bot.send_message(msg.chat.id, text)
+        .parse_mode(ParseMode::Html)
+        .reply_markup(kb)
+        .await?;
+
+    Ok(())
+}
+
+/// Subjects of the major, the ones the user attends are checked.
+///
+/// Buttons carry the id of one of the subject's lessons, since subject names may be too
+/// long for the callback data.
+async fn make_subjects_keyboard(
+    storage: &dyn Storage,
+    user_id: i64,
+    major_id: &str,
+) -> Result<InlineKeyboardMarkup> {
+    let subjects = storage.get_subjects_by_major(major_id).await?;
+    let electives = storage.get_electives(user_id).await?;
+
+    let keyboard = subjects
+        .iter()
+        .map(|subject| {
+            let is_chosen = electives.iter().any(|elective| {
+                elective.major_id == major_id && elective.subject_name == subject.subject_name
+            });
+
+            let text = if is_chosen {
+                format!("✅ {}", subject.subject_name)
+            } else {
+                subject.subject_name.clone()
+            };
+
+            let data = format!("{}:{}", ButtonPrefix::ToggleElective, subject.id);
+            vec![InlineKeyboardButton::callback(text, data)]
+        })
+        .collect::<Vec<_>>();
+
+    Ok(InlineKeyboardMarkup::new(keyboard))
+}
+
+pub async fn elective_major_callback_handler(
+    storage: Arc<dyn Storage>,
+    bot: Bot,
+    q: CallbackQuery,
+) -> Result<()> {
+    let Some(data) = q.data else { return Ok(()) };
+
+    let Some((button_prefix, major_id)) = data.split_once(':') else {
+        return Ok(());
+    };
+
+    if button_prefix != format!("{}", ButtonPrefix::ElectiveMajor) {
+        return Ok(());
+    };
+
+    let user_id = i64::try_from(q.from.id.0)?;
+    let lang = storage.get_user_language(user_id).await?;
+
+    let Some(user_entry) = storage.get_user(user_id).await? else {
+        return Err(BotError::User(tr(lang, "personal-major-required").to_owned()).into());
+    };
+
+    if user_entry.major_id == major_id {
+        return Err(BotError::User(tr(lang, "elective-own-major").to_owned()).into());
+    }
+
+    bot.answer_callback_query(q.id).await?;
+
+    let Some(major) = storage.get_major(major_id).await? else {
+        return Err(BotError::NotFound(format!("major {major_id}")).into());
+    };
+
+    let kb = make_subjects_keyboard(storage.as_ref(), user_id, &major.id).await?;
+    let text = if kb.inline_keyboard.is_empty() {
+        format!("<i>{}</i>", tr(lang, "nothing-found"))
+    } else {
+        tr_args(lang, "choose-electives", &[("major", &major.title)])
+    };
+
+    if let Some(Message { id, chat, .. }) = q.message {
+        bot.edit_message_text(chat.id, id, text)
+            .parse_mode(ParseMode::Html)
+            .reply_markup(kb)
+            .await?;
+    }
+
+    Ok(())
+}
+
+pub async fn toggle_elective_callback_handler(
+    storage: Arc<dyn Storage>,
+    bot: Bot,
+    q: CallbackQuery,
+) -> Result<()> {
+    let Some(data) = q.data else { return Ok(()) };
+
+    let Some((button_prefix, timetable_id)) = data.split_once(':') else {
+        return Ok(());
+    };
+
+    if button_prefix != format!("{}", ButtonPrefix::ToggleElective) {
+        return Ok(());
+    };
+
+    let timetable_id: i64 = timetable_id.parse()?;
+    let user_id = i64::try_from(q.from.id.0)?;
+    let lang = storage.get_user_language(user_id).await?;
+
+    // the lesson might have been deleted since the keyboard was sent
+    let entries = storage
+        .get_timetable_entries_by_ids(&[timetable_id])
+        .await?;
+    let Some((major_id, subject_name)) = entries
+        .into_iter()
+        .find_map(|entry| Some((entry.major_id?, entry.subject_name)))
+    else {
+        return Err(BotError::NotFound(format!("lesson #{timetable_id}")).into());
+    };
+
+    let key = if storage
+        .delete_elective(user_id, &major_id, &subject_name)
+        .await?
+    {
+        "elective-removed"
+    } else {
+        storage
+            .insert_elective(user_id, &major_id, &subject_name)
+            .await?;
+        "elective-added"
+    };
+
+    bot.answer_callback_query(q.id)
+        .text(tr_args(lang, key, &[("subject", &subject_name)]))
+        .await?;
+
+    if let Some(Message { id, chat, .. }) = q.message {
+        let kb = make_subjects_keyboard(storage.as_ref(), user_id, &major_id).await?;
+        bot.edit_message_reply_markup(chat.id, id)
+            .reply_markup(kb)
+            .await?;
+    }
+
+    Ok(())
+}
@@ -0,0 +1,256 @@
+use std::{
+    collections::HashMap,
+    ops::ControlFlow,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use anyhow::Result;
+use teloxide::{
+    dispatching::{DpHandlerDescription, UpdateHandler},
+    dptree::{
+        di::{DependencyMap, DependencySupplier},
+        HandlerDescription,
+    },
+    prelude::*,
+    types::{Update, UpdateKind},
+};
+use thiserror::Error;
+
+use crate::{
+    config::AppConfig,
+    utils::{
+        i18n::{tr, Language},
+        storage::Storage,
+    },
+};
+
+/// Errors which handlers return to tell the user what went wrong,
+/// any other error is reported as an internal one.
+#[derive(Debug, Error)]
+pub enum BotError {
+    /// The request can't be fulfilled as it is, the message is shown to the user as is,
+    /// so it must be in their language.
+    #[error("{0}")]
+    User(String),
+    /// The request refers to something which doesn't exist, e.g. a deleted lesson.
+    #[error("{0} not found")]
+    NotFound(String),
+    /// A failure the user can't do anything about, the owners are notified about it.
+    #[error(transparent)]
+    Internal(#[from] anyhow::Error),
+}
+
+impl BotError {
+    fn find(err: &anyhow::Error) -> Option<&Self> {
+        err.chain().find_map(|cause| cause.downcast_ref::<Self>())
+    }
+
+    fn is_internal(err: &anyhow::Error) -> bool {
+        matches!(Self::find(err), None | Some(Self::Internal(_)))
+    }
+}
+
+/// How often the owners are told about the same kind of internal error.
+const OWNER_NOTICE_INTERVAL: Duration = Duration::from_secs(10 * 60);
+
+/// Remembers when the owners were told about each kind of internal error,
+/// so that e.g. a database outage doesn't flood them with messages.
+#[derive(Debug, Clone, Default)]
+struct OwnerNotices {
+    /// The root cause of the error, when it was reported and how many times it was suppressed since.
+    sent: Arc<Mutex<HashMap<String, (Instant, usize)>>>,
+}
+
+impl OwnerNotices {
+    /// `Some` with the number of suppressed errors of the kind if the owners should be told now.
+    fn check(&self, kind: &str, now: Instant) -> Option<usize> {
+        let mut sent = self.sent.lock().unwrap();
+
+        sent.retain(|_, (reported_at, suppressed)| {
+            *suppressed > 0 || now.duration_since(*reported_at) < OWNER_NOTICE_INTERVAL
+        });
+
+        match sent.get_mut(kind) {
+            Some((reported_at, suppressed))
+                if now.duration_since(*reported_at) < OWNER_NOTICE_INTERVAL =>
+            {
+                *suppressed += 1;
+                None
+            }
+            Some((reported_at, suppressed)) => {
+                let count = *suppressed;
+                *reported_at = now;
+                *suppressed = 0;
+                Some(count)
+            }
+            None => {
+                sent.insert(kind.to_owned(), (now, 0));
+                Some(0)
+            }
+        }
+    }
+}
+
+/// Reports failures of the handlers chained after it to the user whose update failed.
+///
+/// Only internal errors are passed on to the dispatcher's error handler,
+/// the other ones are handled once the user is told about them.
+pub fn report_errors() -> UpdateHandler<anyhow::Error> {
+    let notices = OwnerNotices::default();
+
+    dptree::from_fn_with_description(
+        DpHandlerDescription::entry(),
+        move |deps: DependencyMap, cont| {
+            let notices = notices.clone();
+
+            async move {
+                let update: Arc<Update> = deps.get();
+                let bot: Arc<Bot> = deps.get();
+                let storage: Arc<Arc<dyn Storage>> = deps.get();
+                let cfg: Arc<Arc<AppConfig>> = deps.get();
+
+                let result = cont(deps).await;
+
+                let ControlFlow::Break(Err(err)) = result else {
+                    return result;
+                };
+
+                let storage = storage.as_ref().as_ref();
+                if let Err(report_err) =
+                    report_error(&bot, storage, &cfg, &notices, &update, &err).await
+                {
+                    log::error!(
+                        "Failed to report an error of update #{}: {report_err}",
+                        update.id
+                    );
+                }
+
+                if BotError::is_internal(&err) {
+                    ControlFlow::Break(Err(err))
+                } else {
+                    ControlFlow::Break(Ok(()))
+                }
+            }
+        },
+    )
+}
+
+async fn report_error(
+    bot: &Bot,
+    storage: &dyn Storage,
+    cfg: &AppConfig,
+    notices: &OwnerNotices,
+    update: &Update,
+    err: &anyhow::Error,
+) -> Result<()> {
+    // the database might be the reason of the failure
+    let lang = match update.user() {
+        Some(user) => storage
+            .get_user_language(i64::try_from(user.id.0)?)
+            .await
+            .unwrap_or_default(),
+        None => Language::default(),
+    };
+
+    let text = match BotError::find(err) {
+        Some(BotError::User(text)) => text.clone(),
+        Some(BotError::NotFound(_)) => tr(lang, "error-not-found").to_owned(),
+        _ => tr(lang, "error-internal").to_owned(),
+    };
+
+    match &update.kind {
+        UpdateKind::Message(msg) => {
+            bot.send_message(msg.chat.id, text).await?;
+        }
+
+        UpdateKind::CallbackQuery(q) => {
+            // most handlers answer the query right away, then the message is sent instead
+            let answered = bot
+                .answer_callback_query(&q.id)
+                .text(&text)
+                .show_alert(true)
+                .await;
+
+            if let (Err(_), Some(msg)) = (answered, q.message.as_ref()) {
+                bot.send_message(msg.chat.id, text).await?;
+            }
+        }
+
+        _ => {}
+    }
+
+    if BotError::is_internal(err) {
+        match notices.check(&err.root_cause().to_string(), Instant::now()) {
+            Some(suppressed) => notify_owners(bot, cfg, update, err, suppressed).await,
+            None => log::warn!("Not notifying the owners about a repeated error: {err:#}"),
+        }
+    }
+
+    Ok(())
+}
+
+async fn notify_owners(
+    bot: &Bot,
+    cfg: &AppConfig,
+    update: &Update,
+    err: &anyhow::Error,
+    suppressed: usize,
+) {
+    let author = match update.user() {
+        Some(user) => format!(" от #{}", user.id),
+        None => String::new(),
+    };
+    let mut text = format!(
+        "Ошибка при обработке обновления #{}{author}:\n{err:#}",
+        update.id
+    );
+
+    if suppressed > 0 {
+        text = format!(
+            "{text}\n\nС прошлого сообщения такая ошибка повторилась ещё {suppressed} раз."
+        );
+    }
+
+    for owner_id in &cfg.telegram.owner_ids {
+        let Ok(chat_id) = i64::try_from(*owner_id) else {
+            continue;
+        };
+
+        if let Err(notify_err) = bot.send_message(ChatId(chat_id), &text).await {
+            log::warn!("Failed to notify #{owner_id} about an error: {notify_err}");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn repeated_errors_are_suppressed_until_the_interval_passes() {
+        let notices = OwnerNotices::default();
+        let start = Instant::now();
+
+        assert_eq!(notices.check("pool timed out", start), Some(0));
+        assert_eq!(notices.check("pool timed out", start), None);
+        assert_eq!(
+            notices.check("pool timed out", start + Duration::from_secs(60)),
+            None
+        );
+
+        let later = start + OWNER_NOTICE_INTERVAL;
+        assert_eq!(notices.check("pool timed out", later), Some(2));
+        assert_eq!(notices.check("pool timed out", later), None);
+    }
+
+    #[test]
+    fn different_errors_are_throttled_separately() {
+        let notices = OwnerNotices::default();
+        let now = Instant::now();
+
+        assert_eq!(notices.check("pool timed out", now), Some(0));
+        assert_eq!(notices.check("message is too long", now), Some(0));
+        assert_eq!(notices.check("pool timed out", now), None);
+    }
+}
@@ -1,3 +1,5 @@
+use std::sync::Arc;
+
 use anyhow::Result;
 use teloxide::{
     prelude::*,
@@ -5,12 +7,15 @@ use teloxide::{
     utils::command::BotCommands,
 };
 
-use crate::{utils::{
-    database::Database,
-    sql::types::{MajorEntry, UserEntry},
-}, button_prefix::ButtonPrefix};
+use crate::{
+    button_prefix::ButtonPrefix,
+    utils::{
+        i18n::{tr, tr_args, Language},
+        storage::Storage,
+    },
+};
 
-use super::TimetableCommand;
+use super::error::BotError;
 
 #[derive(BotCommands, Clone)]
 #[command(
@@ -22,54 +27,94 @@ pub enum GeneralCommand {
     Help,
     #[command(description = "Установить свою группу")]
     SetMajor,
+    #[command(description = "Установить группу чата (для администраторов)")]
+    SetChatMajor,
+    #[command(description = "Выбрать подгруппу внутри своей группы")]
+    Subgroup,
+    #[command(description = "Сменить язык / Change the language")]
+    Language,
+    #[command(description = "Где и когда ведёт занятия преподаватель: /professor <фамилия>")]
+    Professor(String),
+    #[command(description = "Свободные аудитории: /rooms [ЧЧ:ММ]")]
+    Rooms(String),
+    #[command(description = "Занятия в аудитории сегодня: /room <номер>")]
+    Room(String),
 }
 
 pub async fn general_commands_handler(
-    db: Database,
+    storage: Arc<dyn Storage>,
     bot: Bot,
     msg: Message,
     cmd: GeneralCommand,
 ) -> Result<()> {
+    let lang = match msg.from() {
+        Some(author) => {
+            storage
+                .get_user_language(i64::try_from(author.id.0)?)
+                .await?
+        }
+        None => Language::default(),
+    };
+
     match cmd {
         GeneralCommand::Help => {
-            help_command_handler(&bot, &msg).await?;
+            help_command_handler(&bot, &msg, lang).await?;
         }
 
         GeneralCommand::SetMajor => {
-            set_major_command_handler(&db, &bot, &msg).await?;
+            set_major_command_handler(storage.as_ref(), &bot, &msg, lang).await?;
+        }
+
+        GeneralCommand::SetChatMajor => {
+            set_chat_major_command_handler(storage.as_ref(), &bot, &msg, lang).await?;
+        }
+
+        GeneralCommand::Subgroup => {
+            subgroup_command_handler(storage.as_ref(), &bot, &msg, lang).await?;
+        }
+
+        GeneralCommand::Language => {
+            language_command_handler(&bot, &msg, lang).await?;
+        }
+
+        GeneralCommand::Professor(query) => {
+            super::professor::professor_command_handler(storage.as_ref(), &bot, &msg, &query, lang)
+                .await?;
+        }
+
+        GeneralCommand::Rooms(args) => {
+            super::rooms::rooms_command_handler(storage.as_ref(), &bot, &msg, &args, lang).await?;
+        }
+
+        GeneralCommand::Room(query) => {
+            super::rooms::room_command_handler(storage.as_ref(), &bot, &msg, &query, lang).await?;
         }
     }
 
     Ok(())
 }
 
-async fn help_command_handler(bot: &Bot, msg: &Message) -> Result<()> {
-    let s = format!(
-        "{}\n\n{}",
-        GeneralCommand::descriptions(),
-        TimetableCommand::descriptions()
-    );
-
-    bot.send_message(msg.chat.id, s)
+async fn help_command_handler(bot: &Bot, msg: &Message, lang: Language) -> Result<()> {
+    bot.send_message(msg.chat.id, tr(lang, "help"))
         .parse_mode(ParseMode::Html)
         .await?;
 
     Ok(())
 }
 
-async fn make_majors_keyboard(db: &Database) -> Result<InlineKeyboardMarkup> {
+pub(super) async fn make_majors_keyboard(
+    storage: &dyn Storage,
+    prefix: ButtonPrefix,
+) -> Result<InlineKeyboardMarkup> {
     let mut keyboard: Vec<Vec<InlineKeyboardButton>> = vec![];
 
-    let sql = r#"SELECT * FROM majors;"#;
-    let majors = sqlx::query_as::<_, MajorEntry>(sql)
-        .fetch_all(db.pool.as_ref())
-        .await?;
+    let majors = storage.get_majors().await?;
 
     for major in majors.chunks(3) {
         let row = major
             .iter()
             .map(|major| {
-                let data = format!("{}:{}", ButtonPrefix::SetMajor, major.id);
+                let data = format!("{}:{}", prefix, major.id);
                 InlineKeyboardButton::callback(&major.title, data)
             })
             .collect();
@@ -79,17 +124,27 @@ async fn make_majors_keyboard(db: &Database) -> Result<InlineKeyboardMarkup> {
 
     Ok(InlineKeyboardMarkup::new(keyboard))
 }
-pub async fn set_major_command_handler(db: &Database, bot: &Bot, msg: &Message) -> Result<()> {
-    let kb = make_majors_keyboard(db).await?;
 
-    bot.send_message(msg.chat.id, "Выберите свою группу")
+pub async fn set_major_command_handler(
+    storage: &dyn Storage,
+    bot: &Bot,
+    msg: &Message,
+    lang: Language,
+) -> Result<()> {
+    let kb = make_majors_keyboard(storage, ButtonPrefix::SetMajor).await?;
+
+    bot.send_message(msg.chat.id, tr(lang, "choose-major"))
         .reply_markup(kb)
         .await?;
 
     Ok(())
 }
 
-pub async fn set_major_callback_handler(db: Database, bot: Bot, q: CallbackQuery) -> Result<()> {
+pub async fn set_major_callback_handler(
+    storage: Arc<dyn Storage>,
+    bot: Bot,
+    q: CallbackQuery,
+) -> Result<()> {
     let Some(data) = q.data else {
         return Ok(())
     };
@@ -106,20 +161,142 @@ pub async fn set_major_callback_handler(db: Database, bot: Bot, q: CallbackQuery
 
     let user_id = i64::try_from(q.from.id.0)?;
 
-    let query = r#"INSERT INTO users (id, major_id) VALUES($1, $2) ON CONFLICT (id) DO UPDATE SET major_id = $2 RETURNING *;"#;
-    let user_entry = sqlx::query_as::<_, UserEntry>(query)
-        .bind(user_id)
-        .bind(new_major_id)
-        .fetch_one(db.pool.as_ref())
-        .await?;
+    let Some(major_entry) = storage.get_major(new_major_id).await? else {
+        return Err(BotError::NotFound(format!("major {new_major_id}")).into());
+    };
+
+    storage.set_user_major(user_id, &major_entry.id).await?;
+
+    let lang = storage.get_user_language(user_id).await?;
+    let mut text = tr_args(lang, "major-changed", &[("major", &major_entry.title)]);
+
+    let kb = make_subgroups_keyboard(storage.as_ref(), &major_entry.id, lang).await?;
+    if kb.is_some() {
+        text = format!("{text}\n\n{}", tr(lang, "choose-subgroup"));
+    }
+
+    if let Some(Message { id, chat, .. }) = q.message {
+        let mut request = bot
+            .edit_message_text(chat.id, id, text)
+            .parse_mode(ParseMode::Html);
+        if let Some(kb) = kb {
+            request = request.reply_markup(kb);
+        }
+        request.await?;
+    } else if let Some(id) = q.inline_message_id {
+        let mut request = bot
+            .edit_message_text_inline(id, text)
+            .parse_mode(ParseMode::Html);
+        if let Some(kb) = kb {
+            request = request.reply_markup(kb);
+        }
+        request.await?;
+    }
+
+    Ok(())
+}
+
+/// `None` if the major is not split into subgroups.
+async fn make_subgroups_keyboard(
+    storage: &dyn Storage,
+    major_id: &str,
+    lang: Language,
+) -> Result<Option<InlineKeyboardMarkup>> {
+    let subgroups = storage.get_subgroups(major_id).await?;
+    if subgroups.is_empty() {
+        return Ok(None);
+    }
 
-    let query = r#"SELECT * FROM majors WHERE id = $1;"#;
-    let major_entry = sqlx::query_as::<_, MajorEntry>(query)
-        .bind(&user_entry.major_id)
-        .fetch_one(db.pool.as_ref())
+    let mut keyboard = subgroups
+        .chunks(3)
+        .map(|chunk| {
+            chunk
+                .iter()
+                .map(|subgroup| {
+                    let data = format!("{}:{}", ButtonPrefix::SetSubgroup, subgroup.name);
+                    InlineKeyboardButton::callback(&subgroup.name, data)
+                })
+                .collect::<Vec<_>>()
+        })
+        .collect::<Vec<_>>();
+
+    // an empty name stands for the whole major
+    let data = format!("{}:", ButtonPrefix::SetSubgroup);
+    keyboard.push(vec![InlineKeyboardButton::callback(
+        tr(lang, "button-whole-major"),
+        data,
+    )]);
+
+    Ok(Some(InlineKeyboardMarkup::new(keyboard)))
+}
+
+async fn subgroup_command_handler(
+    storage: &dyn Storage,
+    bot: &Bot,
+    msg: &Message,
+    lang: Language,
+) -> Result<()> {
+    let Some(author) = msg.from() else {
+        return Ok(());
+    };
+
+    let user_id = i64::try_from(author.id.0)?;
+    let Some(major_id) = storage.get_major_id_for(user_id, None).await? else {
+        bot.send_message(msg.chat.id, tr(lang, "personal-major-required"))
+            .await?;
+        return Ok(());
+    };
+
+    let Some(kb) = make_subgroups_keyboard(storage, &major_id, lang).await? else {
+        bot.send_message(msg.chat.id, tr(lang, "no-subgroups"))
+            .await?;
+        return Ok(());
+    };
+
+    bot.send_message(msg.chat.id, tr(lang, "choose-subgroup"))
+        .reply_markup(kb)
         .await?;
 
-    let text = format!("Вы успешно сменили группу на <b>{}</b>!", major_entry.title);
+    Ok(())
+}
+
+pub async fn set_subgroup_callback_handler(
+    storage: Arc<dyn Storage>,
+    bot: Bot,
+    q: CallbackQuery,
+) -> Result<()> {
+    let Some(data) = q.data else { return Ok(()) };
+
+    let Some((button_prefix, name)) = data.split_once(':') else {
+        return Ok(());
+    };
+
+    if button_prefix != format!("{}", ButtonPrefix::SetSubgroup) {
+        return Ok(());
+    };
+
+    bot.answer_callback_query(q.id).await?;
+
+    let user_id = i64::try_from(q.from.id.0)?;
+    let lang = storage.get_user_language(user_id).await?;
+
+    // the major or its subgroups might have changed since the keyboard was sent
+    let Some(major_id) = storage.get_major_id_for(user_id, None).await? else {
+        return Ok(());
+    };
+
+    let text = if name.is_empty() {
+        storage.set_user_subgroup(user_id, None).await?;
+        tr(lang, "subgroup-cleared").to_owned()
+    } else {
+        let subgroups = storage.get_subgroups(&major_id).await?;
+        if !subgroups.iter().any(|subgroup| subgroup.name == name) {
+            return Ok(());
+        }
+
+        storage.set_user_subgroup(user_id, Some(name)).await?;
+        tr_args(lang, "subgroup-changed", &[("subgroup", name)])
+    };
 
     if let Some(Message { id, chat, .. }) = q.message {
         bot.edit_message_text(chat.id, id, text)
@@ -133,3 +310,141 @@ pub async fn set_major_callback_handler(db: Database, bot: Bot, q: CallbackQuery
 
     Ok(())
 }
+
+/// Only administrators of a group chat may change its default major.
+async fn is_chat_admin(bot: &Bot, chat_id: ChatId, user_id: UserId) -> Result<bool> {
+    let member = bot.get_chat_member(chat_id, user_id).await?;
+    Ok(member.is_privileged())
+}
+
+pub async fn set_chat_major_command_handler(
+    storage: &dyn Storage,
+    bot: &Bot,
+    msg: &Message,
+    lang: Language,
+) -> Result<()> {
+    if msg.chat.is_private() {
+        bot.send_message(msg.chat.id, tr(lang, "chat-major-group-only"))
+            .await?;
+        return Ok(());
+    }
+
+    // anonymous administrators write on behalf of the chat itself
+    let is_anonymous_admin = msg.sender_chat().map(|chat| chat.id) == Some(msg.chat.id);
+
+    let is_admin = match msg.from() {
+        _ if is_anonymous_admin => true,
+        Some(author) => is_chat_admin(bot, msg.chat.id, author.id).await?,
+        None => false,
+    };
+
+    if !is_admin {
+        bot.send_message(msg.chat.id, tr(lang, "chat-major-admins-only"))
+            .await?;
+        return Ok(());
+    }
+
+    let kb = make_majors_keyboard(storage, ButtonPrefix::SetChatMajor).await?;
+
+    bot.send_message(msg.chat.id, tr(lang, "choose-chat-major"))
+        .reply_markup(kb)
+        .await?;
+
+    Ok(())
+}
+
+pub async fn set_chat_major_callback_handler(
+    storage: Arc<dyn Storage>,
+    bot: Bot,
+    q: CallbackQuery,
+) -> Result<()> {
+    let Some(data) = q.data else { return Ok(()) };
+
+    let Some((button_prefix, new_major_id)) = data.split_once(':') else {
+        return Ok(());
+    };
+
+    if button_prefix != format!("{}", ButtonPrefix::SetChatMajor) {
+        return Ok(());
+    };
+
+    let Some(Message { id, chat, .. }) = q.message else {
+        return Ok(());
+    };
+
+    let lang = storage
+        .get_user_language(i64::try_from(q.from.id.0)?)
+        .await?;
+
+    if !is_chat_admin(&bot, chat.id, q.from.id).await? {
+        bot.answer_callback_query(q.id)
+            .text(tr(lang, "chat-major-admins-only"))
+            .await?;
+        return Ok(());
+    }
+
+    bot.answer_callback_query(q.id).await?;
+
+    let Some(major_entry) = storage.get_major(new_major_id).await? else {
+        return Ok(());
+    };
+
+    storage.set_chat_major(chat.id.0, &major_entry.id).await?;
+
+    let text = tr_args(lang, "chat-major-changed", &[("major", &major_entry.title)]);
+
+    bot.edit_message_text(chat.id, id, text)
+        .parse_mode(ParseMode::Html)
+        .await?;
+
+    Ok(())
+}
+
+async fn language_command_handler(bot: &Bot, msg: &Message, lang: Language) -> Result<()> {
+    let row = Language::ALL
+        .into_iter()
+        .map(|language| {
+            let data = format!("{}:{language}", ButtonPrefix::SetLanguage);
+            InlineKeyboardButton::callback(language.native_name(), data)
+        })
+        .collect::<Vec<_>>();
+
+    bot.send_message(msg.chat.id, tr(lang, "choose-language"))
+        .reply_markup(InlineKeyboardMarkup::new([row]))
+        .await?;
+
+    Ok(())
+}
+
+pub async fn set_language_callback_handler(
+    storage: Arc<dyn Storage>,
+    bot: Bot,
+    q: CallbackQuery,
+) -> Result<()> {
+    let Some(data) = q.data else { return Ok(()) };
+
+    let Some((button_prefix, language)) = data.split_once(':') else {
+        return Ok(());
+    };
+
+    if button_prefix != format!("{}", ButtonPrefix::SetLanguage) {
+        return Ok(());
+    };
+
+    bot.answer_callback_query(q.id).await?;
+
+    let lang: Language = language.parse()?;
+    storage
+        .set_user_language(i64::try_from(q.from.id.0)?, lang)
+        .await?;
+
+    let text = tr(lang, "language-changed");
+
+    if let Some(Message { id, chat, .. }) = q.message {
+        bot.edit_message_text(chat.id, id, text).await?;
+    } else if let Some(id) = q.inline_message_id {
+        bot.edit_message_text_inline(id, text).await?;
+    }
+
+    Ok(())
+}
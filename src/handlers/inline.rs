@@ -0,0 +1,138 @@
+use std::sync::Arc;
+
+use anyhow::Result;
+use chrono::{DateTime, Datelike, FixedOffset, Weekday};
+use teloxide::{
+    prelude::*,
+    types::{
+        InlineQueryResult, InlineQueryResultArticle, InputMessageContent, InputMessageContentText,
+        ParseMode,
+    },
+};
+
+use crate::utils::{
+    i18n::{tr, tr_args},
+    storage::Storage,
+    time::add_days,
+};
+
+use super::schedule::{prepate_text, TimetableScope};
+
+/// Results depend on the user's major, so they are cached only for a short time.
+const CACHE_TIME_SECONDS: u32 = 60;
+
+fn parse_weekday(value: &str) -> Option<Weekday> {
+    let weekday = match value {
+        "пн" | "понедельник" | "mon" | "monday" => Weekday::Mon,
+        "вт" | "вторник" | "tue" | "tuesday" => Weekday::Tue,
+        "ср" | "среда" | "wed" | "wednesday" => Weekday::Wed,
+        "чт" | "четверг" | "thu" | "thursday" => Weekday::Thu,
+        "пт" | "пятница" | "fri" | "friday" => Weekday::Fri,
+        "сб" | "суббота" | "sat" | "saturday" => Weekday::Sat,
+        "вс" | "воскресенье" | "sun" | "sunday" => Weekday::Sun,
+        _ => return None,
+    };
+
+    Some(weekday)
+}
+
+/// Splits a query like `завтра`, `пн cs-21` into the requested days and an optional major id.
+///
+/// Weekdays refer to the closest such day starting from today, today and tomorrow are
+/// returned if no day is given.
+fn parse_query(
+    query: &str,
+    now: DateTime<FixedOffset>,
+) -> Result<(Vec<DateTime<FixedOffset>>, Option<String>)> {
+    let mut days = vec![];
+    let mut major_id = None;
+
+    for token in query.split_whitespace() {
+        // days relative to today
+        let offset = match token.to_lowercase().as_str() {
+            "сегодня" | "today" => Some(0),
+            "завтра" | "tomorrow" => Some(1),
+            "послезавтра" => Some(2),
+            "вчера" | "yesterday" => Some(-1),
+            value => parse_weekday(value).map(|weekday| {
+                let today = now.weekday().num_days_from_monday();
+                let wanted = weekday.num_days_from_monday();
+
+                ((7 + wanted - today) % 7).into()
+            }),
+        };
+
+        match offset {
+            Some(offset) => days.push(add_days(&now, offset)?),
+            None => major_id = Some(token.to_owned()),
+        }
+    }
+
+    if days.is_empty() {
+        days = vec![now, add_days(&now, 1)?];
+    }
+
+    Ok((days, major_id))
+}
+
+fn make_article(id: String, title: String, description: String, text: String) -> InlineQueryResult {
+    let content = InputMessageContentText::new(text).parse_mode(ParseMode::Html);
+    let article = InlineQueryResultArticle::new(id, title, InputMessageContent::Text(content))
+        .description(description);
+
+    InlineQueryResult::Article(article)
+}
+
+pub async fn inline_query_handler(
+    storage: Arc<dyn Storage>,
+    bot: Bot,
+    q: InlineQuery,
+) -> Result<()> {
+    let now = crate::utils::time::now()?;
+    let (days, major_id) = parse_query(&q.query, now)?;
+    let user_id = i64::try_from(q.from.id.0)?;
+    let lang = storage.get_user_language(user_id).await?;
+
+    let major_id = match major_id {
+        Some(major_id) => Some(major_id),
+        None => storage.get_user(user_id).await?.map(|user| user.major_id),
+    };
+
+    let major = match major_id {
+        Some(major_id) => storage.get_major(&major_id).await?,
+        None => None,
+    };
+
+    let mut results = vec![];
+
+    if let Some(major) = major {
+        let scope = TimetableScope::load(storage.as_ref(), user_id, &major.id).await?;
+
+        for (i, day) in days.iter().enumerate() {
+            let text = prepate_text(storage.as_ref(), day, &scope, lang).await?;
+            let date = day.format_localized("%A, %e %B", lang.locale()).to_string();
+            let title = tr_args(lang, "inline-title", &[("date", &date)]);
+
+            results.push(make_article(
+                format!("{i}"),
+                title,
+                major.title.clone(),
+                text,
+            ));
+        }
+    } else {
+        results.push(make_article(
+            "no-major".to_owned(),
+            tr(lang, "inline-no-major-title").to_owned(),
+            tr(lang, "inline-no-major-description").to_owned(),
+            tr(lang, "inline-no-major-text").to_owned(),
+        ));
+    }
+
+    bot.answer_inline_query(q.id, results)
+        .cache_time(CACHE_TIME_SECONDS)
+        .is_personal(true)
+        .await?;
+
+    Ok(())
+}
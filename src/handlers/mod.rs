@@ -1,11 +1,19 @@
 pub mod admin;
+pub mod electives;
+pub mod error;
 pub mod general;
+pub mod inline;
+pub mod overrides;
+pub mod professor;
+pub mod rooms;
 pub mod schedule;
+pub mod subscription;
+pub mod upcoming;
 
 use std::sync::Arc;
 
 use anyhow::Result;
-use chrono::{DateTime, Datelike, Duration, FixedOffset, NaiveDate, Weekday};
+use chrono::{DateTime, Datelike, FixedOffset, Weekday};
 use teloxide::{
     prelude::*,
     types::{InlineKeyboardButton, InlineKeyboardMarkup},
@@ -15,34 +23,29 @@ use teloxide::{
 use crate::{
     button_prefix::ButtonPrefix,
     config::AppConfig,
-    utils::{database::Database, sql::models::get_user_entry_by_id, time::TIME_OFFSET_SECONDS},
+    utils::{
+        i18n::{tr, Language},
+        sql::types::UserEntry,
+        storage::Storage,
+        time::{add_days, day_start, localize, Week},
+    },
 };
 
-enum KeyboardWeek {
-    Next,
-    Current,
-}
-
-/// Creates a keyboard made by buttons in a big column.
-fn make_keyboard(kbd_week: KeyboardWeek) -> Result<InlineKeyboardMarkup> {
-    let dt = crate::utils::time::now()?;
-
-    let week = match kbd_week {
-        KeyboardWeek::Next => dt.iso_week().week() + 1,
-        KeyboardWeek::Current => dt.iso_week().week(),
-    };
-
-    let monday = NaiveDate::from_isoywd_opt(dt.year(), week, Weekday::Mon)
-        .unwrap()
-        .and_hms_opt(8, 0, 0)
-        .unwrap()
-        .and_local_timezone(FixedOffset::east_opt(TIME_OFFSET_SECONDS).unwrap())
-        .unwrap();
-
-    let weekdays = (0..=6)
-        .map(|i| monday + Duration::hours(24 * i))
-        .filter(|dt| dt.weekday() != Weekday::Sun)
-        .collect::<Vec<_>>();
+use self::{error::BotError, schedule::TimetableScope};
+
+/// Buttons for every day of the week except Sunday.
+///
+/// Every button carries `<data_prefix>:<rfc3339 date>` as its callback data.
+fn make_days_keyboard(
+    week: &Week,
+    data_prefix: &str,
+    lang: Language,
+) -> Result<InlineKeyboardMarkup> {
+    let weekdays = week
+        .days()
+        .filter(|date| date.weekday() != Weekday::Sun)
+        .map(day_start)
+        .collect::<Result<Vec<_>>>()?;
 
     let mut keyboard: Vec<Vec<InlineKeyboardButton>> = vec![];
 
@@ -50,12 +53,9 @@ fn make_keyboard(kbd_week: KeyboardWeek) -> Result<InlineKeyboardMarkup> {
         let row = days
             .iter()
             .map(|day| {
-                let prefix = format!("{}", ButtonPrefix::TimetableWeekday);
-                let text = day
-                    .format_localized("%A", chrono::Locale::ru_RU)
-                    .to_string();
+                let text = day.format_localized("%A", lang.locale()).to_string();
 
-                let data = format!("{}:{}", prefix, day.to_rfc3339());
+                let data = format!("{}:{}", data_prefix, day.to_rfc3339());
 
                 InlineKeyboardButton::callback(text, data)
             })
@@ -67,6 +67,71 @@ fn make_keyboard(kbd_week: KeyboardWeek) -> Result<InlineKeyboardMarkup> {
     Ok(InlineKeyboardMarkup::new(keyboard))
 }
 
+/// ◀ / ▶ buttons which page through weeks around the given one.
+///
+/// The buttons carry the Monday of the week at the same time as the day buttons use.
+fn make_week_navigation(week: &Week) -> Result<Vec<InlineKeyboardButton>> {
+    let prefix = format!("{}", ButtonPrefix::TimetableWeek);
+
+    let previous = day_start(week.offset(-1).monday())?;
+    let next = day_start(week.offset(1).monday())?;
+
+    Ok(vec![
+        InlineKeyboardButton::callback("◀", format!("{prefix}:{}", previous.to_rfc3339())),
+        InlineKeyboardButton::callback("▶", format!("{prefix}:{}", next.to_rfc3339())),
+    ])
+}
+
+/// Callback data of the "today" button, resolved when the button is pressed.
+const TODAY_DATA: &str = "today";
+
+/// Buttons under a day's schedule which move to the neighbouring days or to the whole week.
+fn make_day_navigation(dt: &DateTime<FixedOffset>, lang: Language) -> Result<InlineKeyboardMarkup> {
+    let day_prefix = format!("{}", ButtonPrefix::TimetableWeekday);
+    let week_prefix = format!("{}", ButtonPrefix::TimetableWeek);
+
+    let previous = add_days(dt, -1)?;
+    let next = add_days(dt, 1)?;
+    let monday = day_start(Week::containing(dt.date_naive()).monday())?;
+
+    Ok(InlineKeyboardMarkup::new(vec![
+        vec![
+            InlineKeyboardButton::callback("◀", format!("{day_prefix}:{}", previous.to_rfc3339())),
+            InlineKeyboardButton::callback(
+                tr(lang, "button-today"),
+                format!("{day_prefix}:{TODAY_DATA}"),
+            ),
+            InlineKeyboardButton::callback("▶", format!("{day_prefix}:{}", next.to_rfc3339())),
+        ],
+        vec![InlineKeyboardButton::callback(
+            tr(lang, "button-week"),
+            format!("{week_prefix}:{}", monday.to_rfc3339()),
+        )],
+    ]))
+}
+
+/// Timetable scope for a callback query, the chat's default major is used in group chats.
+async fn find_callback_scope(
+    storage: &dyn Storage,
+    q: &CallbackQuery,
+) -> Result<Option<TimetableScope>> {
+    let author_id = i64::try_from(q.from.id.0)?;
+
+    let chat_id = q
+        .message
+        .as_ref()
+        .filter(|msg| !msg.chat.is_private())
+        .map(|msg| msg.chat.id.0);
+
+    let Some(major_id) = storage.get_major_id_for(author_id, chat_id).await? else {
+        return Ok(None);
+    };
+
+    let scope = TimetableScope::load(storage, author_id, &major_id).await?;
+
+    Ok(Some(scope))
+}
+
 #[derive(BotCommands, Clone)]
 #[command(
     rename_rule = "lowercase",
@@ -79,67 +144,208 @@ pub enum TimetableCommand {
     Today,
     #[command(description = "Просмотр расписания на завтра.")]
     Tomorrow,
-    #[command(description = "Выбрать день со следующий недели.")]
+    #[command(description = "Какое занятие идёт сейчас.")]
+    Now,
+    #[command(description = "Когда и где следующее занятие.")]
+    Next,
+    #[command(description = "Расписание на следующую неделю.")]
     NextWeek,
-    #[command(description = "Выбрать день на неделе.")]
+    #[command(description = "Расписание на текущую неделю.")]
     ThisWeek,
+    #[command(description = "Скачать расписание для календаря (.ics).")]
+    Ics,
+    #[command(description = "Ежедневная рассылка расписания: /subscribe [ЧЧ:ММ] [сегодня|завтра]")]
+    Subscribe(String),
+    #[command(description = "Отписаться от ежедневной рассылки.")]
+    Unsubscribe,
+    #[command(description = "Напоминать о занятиях заранее: /remind [минуты|выкл]")]
+    Remind(String),
+    #[command(description = "Элективы из расписания других групп.")]
+    Electives,
+}
+
+/// Personal features need the user's own major, the chat's default one is not enough.
+async fn find_personal_user(
+    storage: &dyn Storage,
+    bot: &Bot,
+    msg: &Message,
+    user_id: i64,
+    lang: Language,
+) -> Result<Option<UserEntry>> {
+    let user_entry = storage.get_user(user_id).await?;
+
+    if user_entry.is_none() {
+        bot.send_message(msg.chat.id, tr(lang, "personal-major-required"))
+            .await?;
+    }
+
+    Ok(user_entry)
 }
 
 pub async fn timetable_commands_handler(
     _config: Arc<AppConfig>,
-    db: Database,
+    storage: Arc<dyn Storage>,
     bot: Bot,
     msg: Message,
     cmd: TimetableCommand,
 ) -> Result<()> {
+    if !self::schedule::filter_predicate(&bot, &msg, storage.as_ref()).await? {
+        return Ok(());
+    }
+
     let dt = crate::utils::time::now()?;
 
-    // user must exist since we do filtering with dptree
+    // user must exist since it is checked by the predicate
     let author = msg.from().unwrap();
     let author_id = i64::try_from(author.id.0)?;
 
-    let user_entry = get_user_entry_by_id(db.pool.as_ref(), author_id).await?;
+    // group chats may have a default major for members who have not set their own
+    let chat_id = (!msg.chat.is_private()).then_some(msg.chat.id.0);
+    let major_id = storage
+        .get_major_id_for(author_id, chat_id)
+        .await?
+        .ok_or(BotError::NotFound("major".to_owned()))?;
+    let scope = TimetableScope::load(storage.as_ref(), author_id, &major_id).await?;
+
+    let lang = storage.get_user_language(author_id).await?;
+    let weekday_prefix = format!("{}", ButtonPrefix::TimetableWeekday);
 
     match cmd {
         TimetableCommand::Yesterday => {
-            let dt = dt - Duration::hours(24);
-            self::schedule::command_handler(&db, &bot, dt, &user_entry.major_id, &msg.chat).await?;
+            let dt = add_days(&dt, -1)?;
+            let kb = make_day_navigation(&dt, lang)?;
+            let text = self::schedule::prepate_text(storage.as_ref(), &dt, &scope, lang).await?;
+            self::schedule::send_schedule(&bot, &msg.chat, text, kb).await?;
         }
 
         TimetableCommand::Today => {
-            self::schedule::command_handler(&db, &bot, dt, &user_entry.major_id, &msg.chat).await?;
+            let kb = make_day_navigation(&dt, lang)?;
+            let text = self::schedule::prepate_text(storage.as_ref(), &dt, &scope, lang).await?;
+            self::schedule::send_schedule(&bot, &msg.chat, text, kb).await?;
         }
 
         TimetableCommand::Tomorrow => {
-            let dt = dt + Duration::hours(24);
-            self::schedule::command_handler(&db, &bot, dt, &user_entry.major_id, &msg.chat).await?;
+            let dt = add_days(&dt, 1)?;
+            let kb = make_day_navigation(&dt, lang)?;
+            let text = self::schedule::prepate_text(storage.as_ref(), &dt, &scope, lang).await?;
+            self::schedule::send_schedule(&bot, &msg.chat, text, kb).await?;
         }
 
-        TimetableCommand::ThisWeek => {
-            let kb = make_keyboard(KeyboardWeek::Current)?;
+        TimetableCommand::Now => {
+            self::upcoming::now_command_handler(storage.as_ref(), &bot, &msg, dt, &scope, lang)
+                .await?;
+        }
 
-            bot.send_message(msg.chat.id, "Выберите интересующий вас день текущей недели")
-                .reply_markup(kb)
+        TimetableCommand::Next => {
+            self::upcoming::next_command_handler(storage.as_ref(), &bot, &msg, dt, &scope, lang)
                 .await?;
         }
 
+        TimetableCommand::ThisWeek => {
+            let week = Week::containing(dt.date_naive());
+            let kb = make_days_keyboard(&week, &weekday_prefix, lang)?
+                .append_row(make_week_navigation(&week)?);
+
+            let text =
+                self::schedule::prepare_week_text(storage.as_ref(), &week, &scope, lang).await?;
+            self::schedule::send_schedule(&bot, &msg.chat, text, kb).await?;
+        }
+
         TimetableCommand::NextWeek => {
-            let kb = make_keyboard(KeyboardWeek::Next)?;
+            let week = Week::containing(dt.date_naive()).offset(1);
+            let kb = make_days_keyboard(&week, &weekday_prefix, lang)?
+                .append_row(make_week_navigation(&week)?);
 
-            bot.send_message(
-                msg.chat.id,
-                "Выберите интересующий вас день следующей недели",
+            let text =
+                self::schedule::prepare_week_text(storage.as_ref(), &week, &scope, lang).await?;
+            self::schedule::send_schedule(&bot, &msg.chat, text, kb).await?;
+        }
+
+        TimetableCommand::Ics => {
+            self::schedule::ics_command_handler(
+                storage.as_ref(),
+                &bot,
+                dt,
+                &scope,
+                &msg.chat,
+                lang,
             )
-            .reply_markup(kb)
             .await?;
         }
+
+        TimetableCommand::Subscribe(args) => {
+            if let Some(user_entry) =
+                find_personal_user(storage.as_ref(), &bot, &msg, author_id, lang).await?
+            {
+                self::subscription::subscribe_command_handler(
+                    storage.as_ref(),
+                    &bot,
+                    &msg,
+                    &user_entry,
+                    dt,
+                    &args,
+                    lang,
+                )
+                .await?;
+            }
+        }
+
+        TimetableCommand::Unsubscribe => {
+            if let Some(user_entry) =
+                find_personal_user(storage.as_ref(), &bot, &msg, author_id, lang).await?
+            {
+                self::subscription::unsubscribe_command_handler(
+                    storage.as_ref(),
+                    &bot,
+                    &msg,
+                    &user_entry,
+                    lang,
+                )
+                .await?;
+            }
+        }
+
+        TimetableCommand::Remind(args) => {
+            if let Some(user_entry) =
+                find_personal_user(storage.as_ref(), &bot, &msg, author_id, lang).await?
+            {
+                self::subscription::remind_command_handler(
+                    storage.as_ref(),
+                    &bot,
+                    &msg,
+                    &user_entry,
+                    &args,
+                    lang,
+                )
+                .await?;
+            }
+        }
+
+        TimetableCommand::Electives => {
+            if let Some(user_entry) =
+                find_personal_user(storage.as_ref(), &bot, &msg, author_id, lang).await?
+            {
+                self::electives::electives_command_handler(
+                    storage.as_ref(),
+                    &bot,
+                    &msg,
+                    &user_entry,
+                    lang,
+                )
+                .await?;
+            }
+        }
     };
 
     Ok(())
 }
 
-pub async fn timetable_callback_handler(db: Database, bot: Bot, q: CallbackQuery) -> Result<()> {
-    let Some(data) = q.data else {
+pub async fn timetable_callback_handler(
+    storage: Arc<dyn Storage>,
+    bot: Bot,
+    q: CallbackQuery,
+) -> Result<()> {
+    let Some(data) = &q.data else {
         return Ok(())
     };
 
@@ -151,21 +357,68 @@ pub async fn timetable_callback_handler(db: Database, bot: Bot, q: CallbackQuery
         return Ok(());
     };
 
-    bot.answer_callback_query(q.id).await?;
+    bot.answer_callback_query(&q.id).await?;
 
-    let dt: DateTime<FixedOffset> = DateTime::parse_from_rfc3339(date_rfc3339)?;
+    let dt: DateTime<FixedOffset> = match date_rfc3339 {
+        TODAY_DATA => crate::utils::time::now()?,
+        value => localize(&DateTime::parse_from_rfc3339(value)?),
+    };
 
-    let author = q.from;
-    let author_id = i64::try_from(author.id.0)?;
+    let Some(scope) = find_callback_scope(storage.as_ref(), &q).await? else {
+        return Ok(());
+    };
 
-    let user_entry = get_user_entry_by_id(db.pool.as_ref(), author_id).await?;
+    let lang = storage
+        .get_user_language(i64::try_from(q.from.id.0)?)
+        .await?;
+    let text = self::schedule::prepate_text(storage.as_ref(), &dt, &scope, lang).await?;
+    let kb = make_day_navigation(&dt, lang)?;
 
     if let Some(Message { id, chat, .. }) = q.message {
-        self::schedule::button_handler_known_chat(&db, &bot, dt, &user_entry.major_id, &chat, id)
-            .await?;
+        self::schedule::button_handler_known_chat(&bot, &chat, id, text, Some(kb)).await?;
     } else if let Some(id) = q.inline_message_id {
-        self::schedule::button_handler_unknown_chat(&db, &bot, dt, &user_entry.major_id, id)
-            .await?;
+        self::schedule::button_handler_unknown_chat(&bot, id, text, Some(kb)).await?;
+    }
+
+    Ok(())
+}
+
+pub async fn week_callback_handler(
+    storage: Arc<dyn Storage>,
+    bot: Bot,
+    q: CallbackQuery,
+) -> Result<()> {
+    let Some(data) = &q.data else { return Ok(()) };
+
+    let Some((button_prefix, date_rfc3339)) = data.split_once(':') else {
+        return Ok(());
+    };
+
+    if button_prefix != format!("{}", ButtonPrefix::TimetableWeek) {
+        return Ok(());
+    };
+
+    bot.answer_callback_query(&q.id).await?;
+
+    let monday = localize(&DateTime::parse_from_rfc3339(date_rfc3339)?);
+    let week = Week::containing(monday.date_naive());
+
+    let Some(scope) = find_callback_scope(storage.as_ref(), &q).await? else {
+        return Ok(());
+    };
+
+    let lang = storage
+        .get_user_language(i64::try_from(q.from.id.0)?)
+        .await?;
+    let text = self::schedule::prepare_week_text(storage.as_ref(), &week, &scope, lang).await?;
+    let weekday_prefix = format!("{}", ButtonPrefix::TimetableWeekday);
+    let kb =
+        make_days_keyboard(&week, &weekday_prefix, lang)?.append_row(make_week_navigation(&week)?);
+
+    if let Some(Message { id, chat, .. }) = q.message {
+        self::schedule::button_handler_known_chat(&bot, &chat, id, text, Some(kb)).await?;
+    } else if let Some(id) = q.inline_message_id {
+        self::schedule::button_handler_unknown_chat(&bot, id, text, Some(kb)).await?;
     }
 
     Ok(())
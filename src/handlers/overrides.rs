@@ -0,0 +1,360 @@
+use anyhow::{anyhow, bail, Result};
+use chrono::{Datelike, NaiveDate, NaiveTime};
+use teloxide::{prelude::*, types::ParseMode};
+
+use crate::{
+    tasks::notifier::Notifier,
+    utils::{
+        durations::LessonDurations,
+        i18n::{tr, tr_args, Language},
+        overrides::{extra_entry, moved_entry},
+        semester::SemesterCalendar,
+        sql::types::{OverrideEntry, OverrideKind, TimeTableEntry, TimeTableKey},
+        storage::Storage,
+    },
+};
+
+use super::{
+    admin::{parse_major, split_args, OWNER_LANGUAGE},
+    schedule::format_entry,
+};
+
+fn parse_date(value: &str) -> Result<NaiveDate> {
+    NaiveDate::parse_from_str(value, "%Y-%m-%d")
+        .map_err(|_| anyhow!("неверная дата {value:?}, ожидалось ГГГГ-ММ-ДД"))
+}
+
+fn parse_time(value: &str) -> Result<NaiveTime> {
+    NaiveTime::parse_from_str(value, "%H:%M")
+        .map_err(|_| anyhow!("неверное время {value:?}, ожидалось ЧЧ:ММ"))
+}
+
+fn empty_override(kind: OverrideKind, date: NaiveDate) -> OverrideEntry {
+    OverrideEntry {
+        id: 0,
+        kind,
+        major_id: None,
+        timetable_id: None,
+        date,
+        new_date: None,
+        starts_at: None,
+        subject_name: None,
+        subject_type: None,
+        auditorium: None,
+        professor: None,
+        duration_minutes: None,
+    }
+}
+
+/// Finds the recurring lesson which takes place on the date at the given time.
+async fn find_occurrence(
+    storage: &dyn Storage,
+    major_id: &str,
+    subgroup: Option<String>,
+    date: NaiveDate,
+    starts_at: NaiveTime,
+) -> Result<Option<TimeTableEntry>> {
+    let calendar = SemesterCalendar::load(storage, major_id).await?;
+    let Some(week) = calendar.week_type(date) else {
+        return Ok(None);
+    };
+
+    let key = TimeTableKey {
+        major_id: major_id.to_owned(),
+        subgroup,
+        week,
+        day_of_week: date.weekday().into(),
+        starts_at,
+    };
+
+    storage.get_timetable_entry_by_key(&key).await
+}
+
+/// An override built from the command arguments together with the lesson it changes.
+type ParsedOverride = (OverrideEntry, Option<TimeTableEntry>);
+
+/// Builds an override from the command arguments, `Err` holds a message for the owner.
+async fn parse_override(
+    storage: &dyn Storage,
+    kind: OverrideKind,
+    args: &str,
+) -> Result<std::result::Result<ParsedOverride, String>> {
+    let args = split_args(args);
+
+    if kind == OverrideKind::Holiday {
+        let entry = match args[..] {
+            [date] => empty_override(kind, parse_date(date)?),
+            [date, major_id] => {
+                if storage.get_major(major_id).await?.is_none() {
+                    return Ok(Err(format!("Группа {major_id} не найдена.")));
+                }
+
+                OverrideEntry {
+                    major_id: Some(major_id.to_owned()),
+                    ..empty_override(kind, parse_date(date)?)
+                }
+            }
+            _ => bail!("ожидалось 1 или 2 аргумента, получено {}", args.len()),
+        };
+
+        return Ok(Ok((entry, None)));
+    }
+
+    let [major_id, date, starts_at, rest @ ..] = &args[..] else {
+        bail!("ожидалось не меньше 3 аргументов, получено {}", args.len())
+    };
+
+    let (major_id, subgroup) = parse_major(major_id)?;
+    let major_id = major_id.as_str();
+    let date = parse_date(date)?;
+    let starts_at = parse_time(starts_at)?;
+
+    if storage.get_major(major_id).await?.is_none() {
+        return Ok(Err(format!("Группа {major_id} не найдена.")));
+    }
+
+    let base = OverrideEntry {
+        major_id: Some(major_id.to_string()),
+        ..empty_override(kind, date)
+    };
+
+    let parsed = match kind {
+        OverrideKind::Cancel | OverrideKind::Move => {
+            let Some(original) =
+                find_occurrence(storage, major_id, subgroup, date, starts_at).await?
+            else {
+                return Ok(Err("В это время у группы нет занятия.".to_owned()));
+            };
+
+            let base = OverrideEntry {
+                timetable_id: Some(original.id),
+                ..base
+            };
+
+            let entry = match (kind, rest) {
+                (OverrideKind::Cancel, []) => base,
+                (OverrideKind::Move, [new_date]) => OverrideEntry {
+                    new_date: Some(parse_date(new_date)?),
+                    ..base
+                },
+                (OverrideKind::Move, [new_date, new_starts_at]) => OverrideEntry {
+                    new_date: Some(parse_date(new_date)?),
+                    starts_at: Some(parse_time(new_starts_at)?),
+                    ..base
+                },
+                (OverrideKind::Move, [new_date, new_starts_at, auditorium]) => OverrideEntry {
+                    new_date: Some(parse_date(new_date)?),
+                    starts_at: Some(parse_time(new_starts_at)?),
+                    auditorium: Some(auditorium.to_string()),
+                    ..base
+                },
+                _ => bail!("неверное число аргументов: {}", args.len()),
+            };
+
+            (entry, Some(original))
+        }
+
+        OverrideKind::Extra => {
+            if subgroup.is_some() {
+                bail!("разовые занятия проводятся для всей группы, уберите подгруппу");
+            }
+
+            let (subject_name, subject_type, auditorium, professor) = match rest {
+                [name, kind, auditorium] => (name, kind, auditorium, None),
+                [name, kind, auditorium, professor] => (name, kind, auditorium, Some(professor)),
+                _ => bail!("ожидалось 6 или 7 аргументов, получено {}", args.len()),
+            };
+
+            let durations = LessonDurations::load(storage).await?;
+
+            let entry = OverrideEntry {
+                starts_at: Some(starts_at),
+                duration_minutes: Some(durations.minutes_for(major_id, subject_type)),
+                subject_name: Some(subject_name.to_string()),
+                subject_type: Some(subject_type.to_string()),
+                auditorium: Some(auditorium.to_string()),
+                professor: professor.map(|value| value.to_string()),
+                ..base
+            };
+
+            (entry, None)
+        }
+
+        OverrideKind::Holiday => unreachable!("holidays are parsed above"),
+    };
+
+    Ok(Ok(parsed))
+}
+
+pub fn format_override(entry: &OverrideEntry) -> String {
+    let major = entry.major_id.as_deref().unwrap_or("*");
+    let date = entry.date.format("%Y-%m-%d");
+    let time = |time: Option<NaiveTime>| {
+        time.map(|time| time.format("%H:%M").to_string())
+            .unwrap_or_default()
+    };
+
+    let details = match entry.kind {
+        OverrideKind::Holiday => String::new(),
+        OverrideKind::Cancel => format!(" занятие #{}", entry.timetable_id.unwrap_or_default()),
+        OverrideKind::Move => format!(
+            " занятие #{} → {} {} {}",
+            entry.timetable_id.unwrap_or_default(),
+            entry
+                .new_date
+                .map(|date| date.format("%Y-%m-%d").to_string())
+                .unwrap_or_default(),
+            time(entry.starts_at),
+            entry.auditorium.as_deref().unwrap_or_default()
+        ),
+        OverrideKind::Extra => format!(
+            " {} {} ({})",
+            time(entry.starts_at),
+            entry.subject_name.as_deref().unwrap_or_default(),
+            entry.auditorium.as_deref().unwrap_or_default()
+        ),
+    };
+
+    format!(
+        "#{} {} {major} {date}{}",
+        entry.id,
+        entry.kind,
+        details.trim_end()
+    )
+}
+
+/// Explains the override to the students, `original` is the lesson it changes.
+fn describe_for_students(
+    entry: &OverrideEntry,
+    original: Option<&TimeTableEntry>,
+    lang: Language,
+) -> Result<String> {
+    let date = entry.date.format("%d.%m.%Y").to_string();
+    let args = [("date", date.as_str())];
+
+    let text = match (entry.kind, original) {
+        (OverrideKind::Holiday, _) => tr_args(lang, "notice-holiday", &args),
+        (OverrideKind::Cancel, Some(original)) => format!(
+            "{}\n\n{}",
+            tr_args(lang, "notice-cancelled", &args),
+            format_entry(original, lang)?
+        ),
+        (OverrideKind::Move, Some(original)) => {
+            let new_date = entry
+                .new_date
+                .map(|date| date.format("%d.%m.%Y").to_string())
+                .unwrap_or_default();
+
+            format!(
+                "{}\n\n<i>{}</i>\n{}\n\n<i>{}</i>\n{}",
+                tr_args(
+                    lang,
+                    "notice-moved",
+                    &[("date", &date), ("new_date", &new_date)]
+                ),
+                tr(lang, "notice-before"),
+                format_entry(original, lang)?,
+                tr(lang, "notice-after"),
+                format_entry(&moved_entry(original, entry), lang)?
+            )
+        }
+        (OverrideKind::Extra, _) => match extra_entry(entry) {
+            Some(extra) => format!(
+                "{}\n\n{}",
+                tr_args(lang, "notice-extra", &args),
+                format_entry(&extra, lang)?
+            ),
+            None => tr_args(lang, "notice-extra-short", &args),
+        },
+        (_, None) => tr_args(lang, "notice-changed", &args),
+    };
+
+    Ok(text)
+}
+
+pub async fn add_override_command_handler(
+    storage: &dyn Storage,
+    notifier: &Notifier,
+    bot: &Bot,
+    msg: &Message,
+    kind: OverrideKind,
+    args: &str,
+) -> Result<()> {
+    let (entry, original) = match parse_override(storage, kind, args).await {
+        Ok(Ok(parsed)) => parsed,
+        Ok(Err(reason)) => {
+            bot.send_message(msg.chat.id, reason).await?;
+            return Ok(());
+        }
+        Err(err) => {
+            bot.send_message(msg.chat.id, format!("Не удалось разобрать данные: {err}"))
+                .await?;
+            return Ok(());
+        }
+    };
+
+    let entry = storage.insert_override(&entry).await?;
+
+    let mut text = format!("<b>Изменение добавлено</b>\n{}", format_override(&entry));
+    if let Some(original) = original.as_ref() {
+        text = format!("{text}\n\n{}", format_entry(original, OWNER_LANGUAGE)?);
+    }
+
+    let major_id = entry.major_id.clone();
+    notifier.notify(major_id.as_deref(), move |lang| {
+        describe_for_students(&entry, original.as_ref(), lang)
+    });
+
+    bot.send_message(msg.chat.id, text)
+        .parse_mode(ParseMode::Html)
+        .await?;
+
+    Ok(())
+}
+
+pub async fn list_overrides_command_handler(
+    storage: &dyn Storage,
+    bot: &Bot,
+    msg: &Message,
+) -> Result<()> {
+    let today = crate::utils::time::now()?.date_naive();
+    let overrides = storage.get_overrides_since(today).await?;
+
+    let text = if overrides.is_empty() {
+        "Предстоящих изменений расписания нет.".to_owned()
+    } else {
+        overrides
+            .iter()
+            .map(format_override)
+            .collect::<Vec<_>>()
+            .join("\n")
+    };
+
+    bot.send_message(msg.chat.id, text).await?;
+
+    Ok(())
+}
+
+pub async fn delete_override_command_handler(
+    storage: &dyn Storage,
+    notifier: &Notifier,
+    bot: &Bot,
+    msg: &Message,
+    id: i64,
+) -> Result<()> {
+    let text = match storage.delete_override(id).await? {
+        Some(entry) => {
+            let date = entry.date.format("%d.%m.%Y").to_string();
+            notifier.notify(entry.major_id.as_deref(), move |lang| {
+                Ok(tr_args(lang, "notice-override-deleted", &[("date", &date)]))
+            });
+
+            format!("Изменение удалено: {}", format_override(&entry))
+        }
+        None => "Такое изменение не найдено.".to_owned(),
+    };
+
+    bot.send_message(msg.chat.id, text).await?;
+
+    Ok(())
+}
@@ -0,0 +1,195 @@
+use std::sync::Arc;
+
+use anyhow::Result;
+use chrono::{DateTime, FixedOffset};
+use teloxide::{
+    prelude::*,
+    types::{InlineKeyboardButton, InlineKeyboardMarkup, ParseMode},
+};
+
+use crate::{
+    button_prefix::ButtonPrefix,
+    utils::{
+        fuzzy::best_matches,
+        i18n::{tr, tr_args, Language},
+        sql::types::ProfessorEntry,
+        storage::Storage,
+        time::{localize, Week},
+    },
+};
+
+use super::{
+    error::BotError,
+    make_days_keyboard,
+    schedule::{find_timetables, format_entry},
+};
+
+/// How many professors are offered if the query is ambiguous.
+const MAX_SUGGESTIONS: usize = 10;
+
+fn weekday_prefix(timetable_id: i64) -> String {
+    format!("{}:{timetable_id}", ButtonPrefix::ProfessorWeekday)
+}
+
+/// Lessons of the professor on the day across all majors, overrides are taken into account.
+async fn prepare_professor_text(
+    storage: &dyn Storage,
+    dt: &DateTime<FixedOffset>,
+    professor: &str,
+    lang: Language,
+) -> Result<String> {
+    let major_ids = storage
+        .get_major_ids_by_professor(professor, dt.date_naive())
+        .await?;
+
+    let entries = find_timetables(storage, dt, &major_ids)
+        .await?
+        .into_iter()
+        .filter(|(_, entry)| entry.professor.as_deref() == Some(professor))
+        .collect::<Vec<_>>();
+
+    let mut s = format!(
+        "<b>{professor}, {}</b>",
+        dt.format_localized("%A, %e %B", lang.locale())
+    );
+
+    if entries.is_empty() {
+        return Ok(format!("{s}\n\n<i>{}</i>", tr(lang, "nothing-found")));
+    }
+
+    for (major_id, entry) in entries {
+        s = format!(
+            "{s}\n\n{}\n    {}",
+            format_entry(&entry, lang)?,
+            tr_args(lang, "lesson-major", &[("major", &major_id)])
+        );
+    }
+
+    Ok(s)
+}
+
+fn make_suggestions_keyboard(
+    professors: &[&ProfessorEntry],
+    dt: &DateTime<FixedOffset>,
+) -> InlineKeyboardMarkup {
+    let keyboard = professors
+        .iter()
+        .take(MAX_SUGGESTIONS)
+        .map(|professor| {
+            let data = format!(
+                "{}:{}",
+                weekday_prefix(professor.timetable_id),
+                dt.to_rfc3339()
+            );
+            vec![InlineKeyboardButton::callback(&professor.name, data)]
+        })
+        .collect::<Vec<_>>();
+
+    InlineKeyboardMarkup::new(keyboard)
+}
+
+pub async fn professor_command_handler(
+    storage: &dyn Storage,
+    bot: &Bot,
+    msg: &Message,
+    query: &str,
+    lang: Language,
+) -> Result<()> {
+    if query.trim().is_empty() {
+        bot.send_message(msg.chat.id, tr(lang, "professor-query-required"))
+            .await?;
+        return Ok(());
+    }
+
+    let dt = crate::utils::time::now()?;
+    let professors = storage.get_professors().await?;
+    let matches = best_matches(query, &professors, |professor| &professor.name);
+
+    match matches[..] {
+        [] => {
+            bot.send_message(msg.chat.id, tr(lang, "professor-not-found"))
+                .await?;
+        }
+
+        [professor] => {
+            let text = prepare_professor_text(storage, &dt, &professor.name, lang).await?;
+            let kb = make_days_keyboard(
+                &Week::containing(dt.date_naive()),
+                &weekday_prefix(professor.timetable_id),
+                lang,
+            )?;
+
+            bot.send_message(msg.chat.id, text)
+                .parse_mode(ParseMode::Html)
+                .reply_markup(kb)
+                .await?;
+        }
+
+        _ => {
+            let kb = make_suggestions_keyboard(&matches, &dt);
+
+            bot.send_message(msg.chat.id, tr(lang, "professor-ambiguous"))
+                .reply_markup(kb)
+                .await?;
+        }
+    }
+
+    Ok(())
+}
+
+pub async fn professor_callback_handler(
+    storage: Arc<dyn Storage>,
+    bot: Bot,
+    q: CallbackQuery,
+) -> Result<()> {
+    let Some(data) = q.data else { return Ok(()) };
+
+    let Some((button_prefix, rest)) = data.split_once(':') else {
+        return Ok(());
+    };
+
+    if button_prefix != format!("{}", ButtonPrefix::ProfessorWeekday) {
+        return Ok(());
+    };
+
+    let Some((timetable_id, date_rfc3339)) = rest.split_once(':') else {
+        return Ok(());
+    };
+
+    bot.answer_callback_query(q.id).await?;
+
+    let timetable_id: i64 = timetable_id.parse()?;
+    let dt = localize(&DateTime::parse_from_rfc3339(date_rfc3339)?);
+
+    // the lesson might have been deleted since the keyboard was sent
+    let entries = storage
+        .get_timetable_entries_by_ids(&[timetable_id])
+        .await?;
+    let Some(professor) = entries.into_iter().find_map(|entry| entry.professor) else {
+        return Err(BotError::NotFound(format!("lesson #{timetable_id}")).into());
+    };
+
+    let lang = storage
+        .get_user_language(i64::try_from(q.from.id.0)?)
+        .await?;
+    let text = prepare_professor_text(storage.as_ref(), &dt, &professor, lang).await?;
+    let kb = make_days_keyboard(
+        &Week::containing(dt.date_naive()),
+        &weekday_prefix(timetable_id),
+        lang,
+    )?;
+
+    if let Some(Message { id, chat, .. }) = q.message {
+        bot.edit_message_text(chat.id, id, text)
+            .parse_mode(ParseMode::Html)
+            .reply_markup(kb)
+            .await?;
+    } else if let Some(id) = q.inline_message_id {
+        bot.edit_message_text_inline(id, text)
+            .parse_mode(ParseMode::Html)
+            .reply_markup(kb)
+            .await?;
+    }
+
+    Ok(())
+}
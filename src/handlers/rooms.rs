@@ -0,0 +1,231 @@
+use anyhow::Result;
+use chrono::{DateTime, FixedOffset, NaiveTime};
+use teloxide::{prelude::*, types::ParseMode};
+
+use crate::utils::{
+    fuzzy::{best_matches, normalize},
+    i18n::{tr, tr_args, Language},
+    sql::types::TimeTableEntry,
+    storage::Storage,
+};
+
+use super::schedule::{find_timetables, format_entry};
+
+/// Lessons of every major on the day, overrides are taken into account.
+async fn find_all_lessons(
+    storage: &dyn Storage,
+    dt: &DateTime<FixedOffset>,
+) -> Result<Vec<(String, TimeTableEntry)>> {
+    let major_ids = storage
+        .get_majors()
+        .await?
+        .into_iter()
+        .map(|major| major.id)
+        .collect::<Vec<_>>();
+
+    find_timetables(storage, dt, &major_ids).await
+}
+
+fn is_busy_at(entry: &TimeTableEntry, time: NaiveTime) -> bool {
+    entry.starts_at <= time && time < entry.ends_at
+}
+
+/// Auditoriums which have no lesson at the time, together with the start of their next lesson.
+fn free_rooms(
+    auditoriums: Vec<String>,
+    lessons: &[(String, TimeTableEntry)],
+    time: NaiveTime,
+) -> Vec<(String, Option<NaiveTime>)> {
+    auditoriums
+        .into_iter()
+        .filter(|auditorium| {
+            !lessons
+                .iter()
+                .any(|(_, entry)| &entry.auditorium == auditorium && is_busy_at(entry, time))
+        })
+        .map(|auditorium| {
+            let next_lesson = lessons
+                .iter()
+                .filter(|(_, entry)| entry.auditorium == auditorium && entry.starts_at > time)
+                .map(|(_, entry)| entry.starts_at)
+                .min();
+
+            (auditorium, next_lesson)
+        })
+        .collect()
+}
+
+pub async fn rooms_command_handler(
+    storage: &dyn Storage,
+    bot: &Bot,
+    msg: &Message,
+    args: &str,
+    lang: Language,
+) -> Result<()> {
+    let dt = crate::utils::time::now()?;
+
+    let time = match args.trim() {
+        "" => dt.time(),
+        value => match NaiveTime::parse_from_str(value, "%H:%M") {
+            Ok(time) => time,
+            Err(_) => {
+                bot.send_message(msg.chat.id, tr(lang, "rooms-invalid-time"))
+                    .await?;
+                return Ok(());
+            }
+        },
+    };
+
+    let auditoriums = storage.get_auditoriums().await?;
+    let lessons = find_all_lessons(storage, &dt).await?;
+    let rooms = free_rooms(auditoriums, &lessons, time);
+
+    let title = tr_args(
+        lang,
+        "rooms-title",
+        &[("time", &time.format("%H:%M").to_string())],
+    );
+    let mut s = format!("<b>{title}</b>\n");
+
+    if rooms.is_empty() {
+        s = format!("{s}\n<i>{}</i>", tr(lang, "rooms-all-busy"));
+    }
+
+    for (auditorium, next_lesson) in rooms {
+        let line = match next_lesson {
+            Some(starts_at) => tr_args(
+                lang,
+                "room-free-until",
+                &[
+                    ("room", &auditorium),
+                    ("time", &starts_at.format("%H:%M").to_string()),
+                ],
+            ),
+            None => tr_args(lang, "room-free-rest-of-day", &[("room", &auditorium)]),
+        };
+        s = format!("{s}\n{line}");
+    }
+
+    bot.send_message(msg.chat.id, s)
+        .parse_mode(ParseMode::Html)
+        .await?;
+
+    Ok(())
+}
+
+/// Auditoriums matching the query, an exact number is preferred to the ones it is a prefix of,
+/// so `301` is found next to `301а`.
+fn find_auditoriums<'a>(query: &str, auditoriums: &'a [String]) -> Vec<&'a String> {
+    let query = normalize(query);
+    let exact = auditoriums.iter().find(|auditorium| {
+        normalize(auditorium)
+            .split_whitespace()
+            .eq(query.split_whitespace())
+    });
+
+    match exact {
+        Some(auditorium) => vec![auditorium],
+        None => best_matches(&query, auditoriums, |auditorium| auditorium),
+    }
+}
+
+pub async fn room_command_handler(
+    storage: &dyn Storage,
+    bot: &Bot,
+    msg: &Message,
+    query: &str,
+    lang: Language,
+) -> Result<()> {
+    if query.trim().is_empty() {
+        bot.send_message(msg.chat.id, tr(lang, "room-query-required"))
+            .await?;
+        return Ok(());
+    }
+
+    let auditoriums = storage.get_auditoriums().await?;
+    let matches = find_auditoriums(query, &auditoriums);
+
+    let auditorium = match matches[..] {
+        [] => {
+            bot.send_message(msg.chat.id, tr(lang, "room-not-found"))
+                .await?;
+            return Ok(());
+        }
+
+        [auditorium] => auditorium,
+
+        _ => {
+            let names = matches
+                .iter()
+                .map(|auditorium| auditorium.as_str())
+                .collect::<Vec<_>>()
+                .join(", ");
+
+            bot.send_message(
+                msg.chat.id,
+                tr_args(lang, "room-ambiguous", &[("rooms", &names)]),
+            )
+            .await?;
+            return Ok(());
+        }
+    };
+
+    let dt = crate::utils::time::now()?;
+    let lessons = find_all_lessons(storage, &dt).await?;
+
+    let date = dt.format_localized("%A, %e %B", lang.locale()).to_string();
+    let title = tr_args(lang, "room-title", &[("room", auditorium), ("date", &date)]);
+    let mut s = format!("<b>{title}</b>");
+
+    let mut is_empty = true;
+    for (major_id, entry) in lessons
+        .iter()
+        .filter(|(_, entry)| &entry.auditorium == auditorium)
+    {
+        s = format!(
+            "{s}\n\n{}\n    {}",
+            format_entry(entry, lang)?,
+            tr_args(lang, "lesson-major", &[("major", major_id)])
+        );
+        is_empty = false;
+    }
+
+    if is_empty {
+        s = format!("{s}\n\n<i>{}</i>", tr(lang, "room-no-lessons"));
+    }
+
+    bot.send_message(msg.chat.id, s)
+        .parse_mode(ParseMode::Html)
+        .await?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn exact_auditorium_beats_a_prefix() {
+        let auditoriums = ["30", "301", "301а", "302", "Спортзал"].map(str::to_owned);
+
+        let cases: &[(&str, &[&str])] = &[
+            ("301", &["301"]),
+            ("30", &["30"]),
+            ("301А", &["301а"]),
+            ("спортзал", &["Спортзал"]),
+            (" 30 ", &["30"]),
+            // no exact match, fuzzy matching takes over
+            ("3", &["30", "301", "301а", "302"]),
+            ("спорт", &["Спортзал"]),
+            ("404", &[]),
+        ];
+
+        for (query, expected) in cases {
+            let matches = find_auditoriums(query, &auditoriums);
+            let matches = matches.iter().map(|name| name.as_str()).collect::<Vec<_>>();
+
+            assert_eq!(&matches, expected, "{query:?}");
+        }
+    }
+}
@@ -1,41 +1,126 @@
 use anyhow::{bail, Result};
-use chrono::{DateTime, Datelike, FixedOffset};
+use chrono::{DateTime, Datelike, FixedOffset, NaiveDate, Weekday};
 use teloxide::{
     prelude::*,
-    types::{Chat, MessageId, ParseMode},
-    Bot,
+    types::{Chat, InlineKeyboardMarkup, InputFile, MessageId, ParseMode},
+    ApiError, Bot, RequestError,
 };
 
 use crate::utils::{
-    database::Database,
-    sql::types::{DayOfWeek, TimeTableEntry, UserEntry, WeekType},
+    i18n::{tr, tr_args, Language},
+    ical::make_calendar,
+    overrides::apply_overrides,
+    semester::SemesterCalendar,
+    sql::types::{DayOfWeek, ElectiveEntry, OverrideEntry, OverrideKind, TimeTableEntry, WeekType},
+    storage::Storage,
+    time::{day_start, Week},
 };
 
-async fn get_user(user_id: &UserId, db: &Database) -> Result<Option<UserEntry>> {
-    let id = i64::try_from(user_id.0).unwrap();
+/// Whose timetable is shown: a major, narrowed down to a subgroup,
+/// with the electives of the user from other majors.
+#[derive(Debug, Clone)]
+pub struct TimetableScope {
+    pub major_id: String,
+    pub subgroup: Option<String>,
+    pub electives: Vec<ElectiveEntry>,
+}
 
-    let result = sqlx::query_as::<_, UserEntry>(r#"SELECT * FROM users WHERE id = $1;"#)
-        .bind(id)
-        .fetch_optional(db.pool.as_ref())
-        .await?;
+impl TimetableScope {
+    pub fn new(major_id: String, subgroup: Option<String>, electives: Vec<ElectiveEntry>) -> Self {
+        // lessons of the own major are shown anyway
+        let electives = electives
+            .into_iter()
+            .filter(|elective| elective.major_id != major_id)
+            .collect();
+
+        Self {
+            major_id,
+            subgroup,
+            electives,
+        }
+    }
+
+    /// The whole major with all of its subgroups.
+    pub fn major(major_id: &str) -> Self {
+        Self::new(major_id.to_owned(), None, vec![])
+    }
+
+    /// The major as the user sees it, the subgroup and the electives are personal,
+    /// so they are used only if it is the user's own major.
+    pub async fn load(storage: &dyn Storage, user_id: i64, major_id: &str) -> Result<Self> {
+        let Some(user) = storage.get_user(user_id).await? else {
+            return Ok(Self::major(major_id));
+        };
+
+        if user.major_id != major_id {
+            return Ok(Self::major(major_id));
+        }
+
+        let electives = storage.get_electives(user_id).await?;
+
+        Ok(Self::new(user.major_id, user.subgroup, electives))
+    }
+
+    /// The own major followed by the majors of the electives.
+    pub fn majors(&self) -> Vec<&str> {
+        let mut majors = vec![self.major_id.as_str()];
+
+        for elective in &self.electives {
+            if !majors.contains(&elective.major_id.as_str()) {
+                majors.push(&elective.major_id);
+            }
+        }
+
+        majors
+    }
+
+    /// Whether the lesson of the major is shown, `major_id` must be one of `majors()`.
+    pub fn includes(&self, major_id: &str, entry: &TimeTableEntry) -> bool {
+        if major_id == self.major_id {
+            return entry.is_for_subgroup(self.subgroup.as_deref());
+        }
 
-    Ok(result)
+        self.electives.iter().any(|elective| {
+            elective.major_id == major_id && elective.subject_name == entry.subject_name
+        })
+    }
 }
 
-pub async fn filter_predicate(bot: &Bot, msg: &Message, db: &Database) -> Result<bool> {
+pub async fn filter_predicate(bot: &Bot, msg: &Message, storage: &dyn Storage) -> Result<bool> {
     let Some(user) = msg.from() else {
         bail!("Объект пользователя не связан с сообщением.")
     };
 
-    let Some(_) = get_user(&user.id, db).await? else {
-        bot.send_message(msg.chat.id, "Вы должны указать свою группу!\nИспользуйте /setmajor").await?;
-        return Ok(false)
+    let user_id = i64::try_from(user.id.0)?;
+    let chat_id = (!msg.chat.is_private()).then_some(msg.chat.id.0);
+
+    let None = storage.get_major_id_for(user_id, chat_id).await? else {
+        return Ok(true);
+    };
+
+    let lang = storage.get_user_language(user_id).await?;
+    let key = if msg.chat.is_private() {
+        "major-required"
+    } else {
+        "major-required-group"
     };
 
-    Ok(true)
+    bot.send_message(msg.chat.id, tr(lang, key)).await?;
+
+    Ok(false)
+}
+
+/// Explains why the entry differs from the usual timetable.
+fn format_note(entry: &TimeTableEntry, lang: Language) -> Option<String> {
+    if entry.extra {
+        return Some(tr(lang, "note-extra").to_owned());
+    }
+
+    let date = entry.moved_from?.format("%d.%m").to_string();
+    Some(tr_args(lang, "note-moved-from", &[("date", &date)]))
 }
 
-fn format_entry(entry: &TimeTableEntry) -> Result<String> {
+pub fn format_entry(entry: &TimeTableEntry, lang: Language) -> Result<String> {
     let mut s = format!(
         "{} – {}",
         entry.starts_at.format("%H:%M"),
@@ -43,20 +128,33 @@ fn format_entry(entry: &TimeTableEntry) -> Result<String> {
     );
 
     s = format!("{s}\n<b>{}</b>", entry.subject_name);
+    if entry.elective {
+        s = format!("{s} ★ {}", entry.major_id.as_deref().unwrap_or_default());
+    }
+    if let Some(subgroup) = entry.subgroup.as_ref() {
+        s = format!("{s} [{subgroup}]");
+    }
     s = format!("{s}\n    {}", entry.subject_type);
     if let Some(value) = entry.professor.as_ref() {
         s = format!("{s}\n    {value}");
     }
     s = format!("{s}\n    {}", entry.auditorium);
+    if let Some(note) = format_note(entry, lang) {
+        s = format!("{s}\n    <i>{note}</i>");
+    }
 
     Ok(s)
 }
 
-fn format_entries(entries: &[TimeTableEntry], dt: &DateTime<FixedOffset>) -> Result<String> {
+fn format_entries(
+    entries: &[TimeTableEntry],
+    dt: &DateTime<FixedOffset>,
+    lang: Language,
+) -> Result<String> {
     let mut s = String::new();
 
     entries.iter().for_each(|entry| {
-        let formatted = format_entry(entry).unwrap();
+        let formatted = format_entry(entry, lang).unwrap();
 
         if !s.is_empty() {
             s = format!("{s}\n\n{formatted}");
@@ -65,90 +163,571 @@ fn format_entries(entries: &[TimeTableEntry], dt: &DateTime<FixedOffset>) -> Res
         }
     });
 
-    s = format!(
-        "<b>Расписание занятий на {}</b>\n\n\n{s}",
-        dt.format_localized("%e %B", chrono::Locale::ru_RU)
-            .to_string()
-            .trim()
-    );
+    let date = dt.format_localized("%e %B", lang.locale()).to_string();
+    let title = tr_args(lang, "schedule-title", &[("date", date.trim())]);
+
+    s = format!("<b>{title}</b>\n\n\n{s}");
 
     Ok(s)
 }
 
-async fn find_timetable(db: &Database, dt: &DateTime<FixedOffset>, major_id: &String) -> Result<Vec<TimeTableEntry>> {
+/// Lessons of the major with all of its subgroups on the day.
+async fn find_major_timetable(
+    storage: &dyn Storage,
+    dt: &DateTime<FixedOffset>,
+    major_id: &str,
+) -> Result<Vec<TimeTableEntry>> {
+    let date = dt.date_naive();
+    let day_of_week: DayOfWeek = dt.weekday().into();
+
+    let calendar = SemesterCalendar::load(storage, major_id).await?;
+    let recurring = match calendar.week_type(date) {
+        Some(week) => {
+            storage
+                .get_timetable_for_day(major_id, week, day_of_week)
+                .await?
+        }
+        None => vec![],
+    };
+
+    let overrides = storage.get_overrides_for_date(major_id, date).await?;
+    let originals = find_moved_originals(storage, date, &overrides).await?;
+
+    Ok(apply_overrides(date, recurring, &overrides, &originals))
+}
+
+/// Recurring lessons which the overrides move to the date.
+async fn find_moved_originals(
+    storage: &dyn Storage,
+    date: NaiveDate,
+    overrides: &[OverrideEntry],
+) -> Result<Vec<TimeTableEntry>> {
+    let moved_ids = overrides
+        .iter()
+        .filter(|entry| entry.kind == OverrideKind::Move && entry.new_date == Some(date))
+        .filter_map(|entry| entry.timetable_id)
+        .collect::<Vec<_>>();
+
+    if moved_ids.is_empty() {
+        return Ok(vec![]);
+    }
+
+    storage.get_timetable_entries_by_ids(&moved_ids).await
+}
+
+/// Lessons of the scope on the day, electives are merged in and marked as such.
+pub async fn find_timetable(
+    storage: &dyn Storage,
+    dt: &DateTime<FixedOffset>,
+    scope: &TimetableScope,
+) -> Result<Vec<TimeTableEntry>> {
+    let mut entries = vec![];
+
+    for major_id in scope.majors() {
+        let timetable = find_major_timetable(storage, dt, major_id).await?;
+
+        entries.extend(
+            timetable
+                .into_iter()
+                .filter(|entry| scope.includes(major_id, entry))
+                .map(|entry| TimeTableEntry {
+                    elective: major_id != scope.major_id,
+                    ..entry
+                }),
+        );
+    }
+
+    entries.sort_by_key(|entry| entry.starts_at);
+
+    Ok(entries)
+}
+
+/// Timetables of several majors with all of their subgroups on the day,
+/// merged and sorted by the start time.
+///
+/// Every table is read once for all of the majors and the rows are split between them here.
+pub async fn find_timetables(
+    storage: &dyn Storage,
+    dt: &DateTime<FixedOffset>,
+    major_ids: &[String],
+) -> Result<Vec<(String, TimeTableEntry)>> {
+    let date = dt.date_naive();
     let day_of_week: DayOfWeek = dt.weekday().into();
-    let week: WeekType = (*dt).into(); // TODO: might something stupid
-
-    let entries = sqlx::query_as::<_, TimeTableEntry>(
-        r#"SELECT * FROM public.timetable 
-        WHERE 
-            week = $1 
-            AND day_of_week = $2
-            AND major_id = $3 
-        ORDER BY starts_at;"#,
-    )
-    .bind(&week)
-    .bind(&day_of_week)
-    .bind(major_id)
-    .fetch_all(db.pool.as_ref())
-    .await?;
+
+    let semesters = storage.get_all_semesters().await?;
+    let lessons = storage
+        .get_timetables_for_day(major_ids, day_of_week)
+        .await?;
+    let overrides = storage.get_all_overrides_for_date(date).await?;
+    let originals = find_moved_originals(storage, date, &overrides).await?;
+
+    let mut entries = vec![];
+
+    for major_id in major_ids {
+        // global semesters and holidays have no major
+        let applies = |owner: &Option<String>| owner.as_deref().unwrap_or(major_id) == major_id;
+
+        let calendar = SemesterCalendar::new(
+            semesters
+                .iter()
+                .filter(|semester| applies(&semester.major_id))
+                .cloned()
+                .collect(),
+        );
+        let recurring = match calendar.week_type(date) {
+            Some(week) => lessons
+                .iter()
+                .filter(|entry| entry.major_id.as_ref() == Some(major_id) && entry.week == week)
+                .cloned()
+                .collect(),
+            None => vec![],
+        };
+        let overrides = overrides
+            .iter()
+            .filter(|entry| applies(&entry.major_id))
+            .cloned()
+            .collect::<Vec<_>>();
+
+        let timetable = apply_overrides(date, recurring, &overrides, &originals);
+        entries.extend(timetable.into_iter().map(|entry| (major_id.clone(), entry)));
+    }
+
+    entries.sort_by_key(|(_, entry)| entry.starts_at);
 
     Ok(entries)
 }
 
-async fn prepate_text(db: &Database, dt: &DateTime<FixedOffset>, major_id: &String) -> Result<String> {
-    let entries = find_timetable(db, dt, major_id).await?;
+pub async fn prepate_text(
+    storage: &dyn Storage,
+    dt: &DateTime<FixedOffset>,
+    scope: &TimetableScope,
+    lang: Language,
+) -> Result<String> {
+    let entries = find_timetable(storage, dt, scope).await?;
     let text = if !entries.is_empty() {
-        format_entries(&entries, dt)?
+        format_entries(&entries, dt, lang)?
     } else {
-        "<i>Ничего не найдено.</i>".to_owned()
+        format!("<i>{}</i>", tr(lang, "nothing-found"))
     };
 
     Ok(text)
 }
 
-pub async fn command_handler(
-    db: &Database,
+fn format_compact_entry(entry: &TimeTableEntry, lang: Language) -> String {
+    let subgroup = match entry.subgroup.as_ref() {
+        Some(subgroup) => format!(" [{subgroup}]"),
+        None => String::new(),
+    };
+
+    let elective = match entry.major_id.as_ref().filter(|_| entry.elective) {
+        Some(major_id) => format!(" ★ {major_id}"),
+        None => String::new(),
+    };
+
+    let mut s = format!(
+        "{}–{} {}{elective}{subgroup} ({}), {}",
+        entry.starts_at.format("%H:%M"),
+        entry.ends_at.format("%H:%M"),
+        entry.subject_name,
+        entry.subject_type,
+        entry.auditorium
+    );
+
+    if let Some(note) = format_note(entry, lang) {
+        s = format!("{s} <i>{note}</i>");
+    }
+
+    s
+}
+
+/// All days of the week, Sunday is shown only if it has lessons.
+pub async fn prepare_week_text(
+    storage: &dyn Storage,
+    week: &Week,
+    scope: &TimetableScope,
+    lang: Language,
+) -> Result<String> {
+    let mut s = tr_args(
+        lang,
+        "week-title",
+        &[
+            ("from", &week.monday().format("%d.%m").to_string()),
+            ("to", &week.sunday().format("%d.%m.%Y").to_string()),
+        ],
+    );
+
+    let calendar = SemesterCalendar::load(storage, &scope.major_id).await?;
+    match calendar.week_type(week.monday()) {
+        Some(WeekType::Odd) => s = format!("{s}, {}", tr(lang, "week-odd")),
+        Some(WeekType::Even) => s = format!("{s}, {}", tr(lang, "week-even")),
+        None => {}
+    }
+
+    s = format!("<b>{s}</b>");
+
+    for date in week.days() {
+        let day = day_start(date)?;
+        let entries = find_timetable(storage, &day, scope).await?;
+
+        if day.weekday() == Weekday::Sun && entries.is_empty() {
+            continue;
+        }
+
+        let title = day.format_localized("%A, %e %B", lang.locale()).to_string();
+        s = format!("{s}\n\n<b>{}</b>", title.replace("  ", " "));
+
+        if entries.is_empty() {
+            s = format!("{s}\n<i>{}</i>", tr(lang, "no-lessons"));
+        }
+
+        for entry in entries.iter() {
+            s = format!("{s}\n{}", format_compact_entry(entry, lang));
+        }
+    }
+
+    Ok(s)
+}
+
+/// Sends a day's or a week's schedule prepared by `prepate_text` or `prepare_week_text`.
+pub async fn send_schedule(
     bot: &Bot,
-    dt: DateTime<FixedOffset>,
-    major_id: &String,
     chat: &Chat,
+    text: String,
+    keyboard: InlineKeyboardMarkup,
 ) -> Result<()> {
-    let text = prepate_text(db, &dt, major_id).await?;
     bot.send_message(chat.id, text)
         .parse_mode(ParseMode::Html)
+        .reply_markup(keyboard)
         .await?;
 
     Ok(())
 }
 
+/// Pressing a button which leads to the shown content, e.g. "today" on today's schedule,
+/// makes Telegram reject the edit, which is not an error for the user.
+fn ignore_not_modified(result: Result<(), RequestError>) -> Result<()> {
+    match result {
+        Err(RequestError::Api(ApiError::MessageNotModified)) => Ok(()),
+        result => Ok(result?),
+    }
+}
+
+/// Replaces the message with the text, the keyboard is removed unless a new one is given.
 pub async fn button_handler_known_chat(
-    db: &Database,
     bot: &Bot,
-    dt: DateTime<FixedOffset>,
-    major_id: &String,
     chat: &Chat,
     message_id: MessageId,
+    text: String,
+    keyboard: Option<InlineKeyboardMarkup>,
 ) -> Result<()> {
-    let text = prepate_text(db, &dt, major_id).await?;
-    bot.edit_message_text(chat.id, message_id, text)
-        .parse_mode(ParseMode::Html)
-        .await?;
+    let mut request = bot
+        .edit_message_text(chat.id, message_id, text)
+        .parse_mode(ParseMode::Html);
 
-    Ok(())
+    if let Some(keyboard) = keyboard {
+        request = request.reply_markup(keyboard);
+    }
+
+    ignore_not_modified(request.await.map(|_| ()))
 }
 
+/// Same as `button_handler_known_chat`, but for messages sent in inline mode.
 pub async fn button_handler_unknown_chat(
-    db: &Database,
     bot: &Bot,
-    dt: DateTime<FixedOffset>,
-    major_id: &String,
     id: String,
+    text: String,
+    keyboard: Option<InlineKeyboardMarkup>,
 ) -> Result<()> {
-    let text = prepate_text(db, &dt, major_id).await?;
-    bot.edit_message_text_inline(id, text)
-        .parse_mode(ParseMode::Html)
+    let mut request = bot
+        .edit_message_text_inline(id, text)
+        .parse_mode(ParseMode::Html);
+
+    if let Some(keyboard) = keyboard {
+        request = request.reply_markup(keyboard);
+    }
+
+    ignore_not_modified(request.await.map(|_| ()))
+}
+
+pub async fn ics_command_handler(
+    storage: &dyn Storage,
+    bot: &Bot,
+    dt: DateTime<FixedOffset>,
+    scope: &TimetableScope,
+    chat: &Chat,
+    lang: Language,
+) -> Result<()> {
+    // every major has its own semesters, which the recurring events are bound to
+    let mut timetables = vec![];
+
+    for major_id in scope.majors() {
+        let entries = storage
+            .get_timetable_by_major(major_id)
+            .await?
+            .into_iter()
+            .filter(|entry| scope.includes(major_id, entry))
+            .map(|entry| TimeTableEntry {
+                elective: major_id != scope.major_id,
+                ..entry
+            })
+            .collect::<Vec<_>>();
+
+        if !entries.is_empty() {
+            let semesters = SemesterCalendar::load(storage, major_id).await?;
+            timetables.push((semesters, entries));
+        }
+    }
+
+    if timetables.is_empty() {
+        bot.send_message(chat.id, format!("<i>{}</i>", tr(lang, "nothing-found")))
+            .parse_mode(ParseMode::Html)
+            .await?;
+        return Ok(());
+    }
+
+    let major_id = &scope.major_id;
+    let name = match storage.get_major(major_id).await? {
+        Some(major) => major.title,
+        None => major_id.to_owned(),
+    };
+
+    let calendar = make_calendar(&name, &timetables, &dt)?;
+    let file = InputFile::memory(calendar.into_bytes()).file_name(format!("{major_id}.ics"));
+
+    bot.send_document(chat.id, file)
+        .caption(tr_args(lang, "ics-caption", &[("major", &name)]))
         .await?;
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::{
+        sql::types::{OverrideEntry, SemesterEntry, SubgroupEntry},
+        storage::{
+            fixtures::{date, lesson, time},
+            memory::{MemoryStorage, MemoryUser, State},
+        },
+        time::local_datetime,
+    };
+
+    /// Thursday of an odd ISO week, no semesters are configured so the parity applies.
+    fn thursday() -> DateTime<FixedOffset> {
+        local_datetime(date(2023, 3, 16), time(8)).unwrap()
+    }
+
+    fn elective(user_id: i64, major_id: &str, subject_name: &str) -> ElectiveEntry {
+        ElectiveEntry {
+            user_id,
+            major_id: major_id.to_owned(),
+            subject_name: subject_name.to_owned(),
+        }
+    }
+
+    fn subjects(entries: &[TimeTableEntry]) -> Vec<&str> {
+        entries
+            .iter()
+            .map(|entry| entry.subject_name.as_str())
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn other_subgroups_are_hidden() {
+        let storage = MemoryStorage::new(State {
+            subgroups: vec![
+                SubgroupEntry {
+                    major_id: "ivt".to_owned(),
+                    name: "1".to_owned(),
+                },
+                SubgroupEntry {
+                    major_id: "ivt".to_owned(),
+                    name: "2".to_owned(),
+                },
+            ],
+            timetable: vec![
+                lesson(1, "ivt", 9, "Физика"),
+                TimeTableEntry {
+                    subgroup: Some("1".to_owned()),
+                    ..lesson(2, "ivt", 11, "Лабораторная 1")
+                },
+                TimeTableEntry {
+                    subgroup: Some("2".to_owned()),
+                    ..lesson(3, "ivt", 11, "Лабораторная 2")
+                },
+            ],
+            ..State::default()
+        });
+
+        let scope = TimetableScope::new("ivt".to_owned(), Some("2".to_owned()), vec![]);
+        let entries = find_timetable(&storage, &thursday(), &scope).await.unwrap();
+        assert_eq!(subjects(&entries), ["Физика", "Лабораторная 2"]);
+
+        let scope = TimetableScope::major("ivt");
+        let entries = find_timetable(&storage, &thursday(), &scope).await.unwrap();
+        assert_eq!(
+            subjects(&entries),
+            ["Физика", "Лабораторная 1", "Лабораторная 2"]
+        );
+    }
+
+    #[tokio::test]
+    async fn electives_are_merged_and_marked() {
+        let storage = MemoryStorage::new(State {
+            users: [(
+                7,
+                MemoryUser {
+                    major_id: Some("ivt".to_owned()),
+                    ..MemoryUser::default()
+                },
+            )]
+            .into(),
+            electives: vec![elective(7, "pm", "Криптография")],
+            timetable: vec![
+                lesson(1, "ivt", 13, "Физика"),
+                lesson(2, "pm", 9, "Криптография"),
+                lesson(3, "pm", 11, "Матанализ"),
+            ],
+            ..State::default()
+        });
+
+        let scope = TimetableScope::load(&storage, 7, "ivt").await.unwrap();
+        let entries = find_timetable(&storage, &thursday(), &scope).await.unwrap();
+
+        assert_eq!(subjects(&entries), ["Криптография", "Физика"]);
+        assert!(entries[0].elective);
+        assert!(!entries[1].elective);
+
+        let text = prepate_text(&storage, &thursday(), &scope, Language::Ru)
+            .await
+            .unwrap();
+        assert!(text.contains("<b>Криптография</b> ★ pm"));
+        assert!(!text.contains("Матанализ"));
+    }
+
+    #[tokio::test]
+    async fn electives_apply_only_to_the_own_major() {
+        let storage = MemoryStorage::new(State {
+            users: [(
+                7,
+                MemoryUser {
+                    major_id: Some("ivt".to_owned()),
+                    ..MemoryUser::default()
+                },
+            )]
+            .into(),
+            electives: vec![elective(7, "pm", "Криптография")],
+            timetable: vec![lesson(1, "pm", 9, "Криптография")],
+            ..State::default()
+        });
+
+        // e.g. the default major of a group chat
+        let scope = TimetableScope::load(&storage, 7, "fiz").await.unwrap();
+        let entries = find_timetable(&storage, &thursday(), &scope).await.unwrap();
+
+        assert!(entries.is_empty());
+    }
+
+    #[tokio::test]
+    async fn cancelled_day_has_nothing_found() {
+        let dt = thursday();
+        let storage = MemoryStorage::new(State {
+            timetable: vec![lesson(1, "ivt", 9, "Физика")],
+            overrides: vec![OverrideEntry {
+                id: 1,
+                kind: OverrideKind::Holiday,
+                major_id: None,
+                timetable_id: None,
+                date: dt.date_naive(),
+                new_date: None,
+                starts_at: None,
+                subject_name: None,
+                subject_type: None,
+                auditorium: None,
+                professor: None,
+                duration_minutes: None,
+            }],
+            ..State::default()
+        });
+
+        let scope = TimetableScope::major("ivt");
+        let text = prepate_text(&storage, &dt, &scope, Language::En)
+            .await
+            .unwrap();
+
+        assert_eq!(text, "<i>Nothing found.</i>");
+    }
+
+    #[tokio::test]
+    async fn timetables_use_the_semesters_and_overrides_of_each_major() {
+        let dt = thursday();
+        let monday = date(2023, 3, 13);
+        let semester = |id, major_id: Option<&str>, first_week| SemesterEntry {
+            id,
+            major_id: major_id.map(str::to_owned),
+            starts_on: monday,
+            ends_on: monday + chrono::Duration::days(90),
+            first_week,
+        };
+
+        let storage = MemoryStorage::new(State {
+            semesters: vec![
+                semester(1, None, WeekType::Even),
+                semester(2, Some("pm"), WeekType::Odd),
+            ],
+            timetable: vec![
+                lesson(1, "ivt", 9, "Физика"),
+                TimeTableEntry {
+                    week: WeekType::Even,
+                    ..lesson(2, "ivt", 11, "Химия")
+                },
+                lesson(3, "pm", 10, "Матанализ"),
+                TimeTableEntry {
+                    week: WeekType::Even,
+                    ..lesson(4, "pm", 12, "Алгебра")
+                },
+                TimeTableEntry {
+                    week: WeekType::Even,
+                    ..lesson(5, "fiz", 8, "Механика")
+                },
+            ],
+            overrides: vec![OverrideEntry {
+                id: 1,
+                kind: OverrideKind::Holiday,
+                major_id: Some("fiz".to_owned()),
+                timetable_id: None,
+                date: dt.date_naive(),
+                new_date: None,
+                starts_at: None,
+                subject_name: None,
+                subject_type: None,
+                auditorium: None,
+                professor: None,
+                duration_minutes: None,
+            }],
+            ..State::default()
+        });
+
+        let major_ids = ["ivt", "pm", "fiz"].map(str::to_owned);
+        let entries = find_timetables(&storage, &dt, &major_ids).await.unwrap();
+        let lessons = entries
+            .iter()
+            .map(|(major_id, entry)| (major_id.as_str(), entry.subject_name.as_str()))
+            .collect::<Vec<_>>();
+
+        assert_eq!(lessons, [("pm", "Матанализ"), ("ivt", "Химия")]);
+
+        for major_id in &major_ids {
+            let scope = TimetableScope::major(major_id);
+            let own = find_timetable(&storage, &dt, &scope).await.unwrap();
+            let grouped = entries
+                .iter()
+                .filter(|(id, _)| id == major_id)
+                .map(|(_, entry)| entry.clone())
+                .collect::<Vec<_>>();
+
+            assert_eq!(subjects(&own), subjects(&grouped));
+        }
+    }
+}
@@ -0,0 +1,164 @@
+use anyhow::Result;
+use chrono::{DateTime, FixedOffset, NaiveTime};
+use teloxide::{prelude::*, types::ParseMode};
+
+use crate::utils::{
+    i18n::{tr, tr_args, Language},
+    sql::types::{DigestSubscription, UserEntry},
+    storage::Storage,
+};
+
+const DEFAULT_SEND_AT: (u32, u32) = (20, 0);
+
+const DEFAULT_LEAD_MINUTES: i16 = 10;
+const MAX_LEAD_MINUTES: i16 = 180;
+
+/// Parses `[HH:MM] [сегодня|завтра]`, the day defaults to today for morning digests.
+/// Returns the first argument which couldn't be parsed on failure.
+fn parse_args(args: &str) -> Result<(NaiveTime, bool), &str> {
+    let mut send_at = NaiveTime::from_hms_opt(DEFAULT_SEND_AT.0, DEFAULT_SEND_AT.1, 0).unwrap();
+    let mut next_day = None;
+
+    for arg in args.split_whitespace() {
+        match arg.to_lowercase().as_str() {
+            "сегодня" | "today" => next_day = Some(false),
+            "завтра" | "tomorrow" => next_day = Some(true),
+            value => match NaiveTime::parse_from_str(value, "%H:%M") {
+                Ok(time) => send_at = time,
+                Err(_) => return Err(arg),
+            },
+        }
+    }
+
+    let noon = NaiveTime::from_hms_opt(12, 0, 0).unwrap();
+    let next_day = next_day.unwrap_or(send_at >= noon);
+
+    Ok((send_at, next_day))
+}
+
+pub async fn subscribe_command_handler(
+    storage: &dyn Storage,
+    bot: &Bot,
+    msg: &Message,
+    user_entry: &UserEntry,
+    dt: DateTime<FixedOffset>,
+    args: &str,
+    lang: Language,
+) -> Result<()> {
+    let (send_at, next_day) = match parse_args(args) {
+        Ok(value) => value,
+        Err(value) => {
+            bot.send_message(
+                msg.chat.id,
+                tr_args(lang, "subscribe-invalid", &[("value", value)]),
+            )
+            .await?;
+            return Ok(());
+        }
+    };
+
+    // don't send today's digest right away if its time has already passed
+    let last_sent_on = (send_at <= dt.time()).then(|| dt.date_naive());
+
+    let subscription = DigestSubscription {
+        user_id: user_entry.id,
+        send_at,
+        next_day,
+        last_sent_on,
+    };
+    storage.upsert_digest_subscription(&subscription).await?;
+
+    let day = if next_day {
+        tr(lang, "subscribe-tomorrow")
+    } else {
+        tr(lang, "subscribe-today")
+    };
+    let text = tr_args(
+        lang,
+        "subscribed",
+        &[("time", &send_at.format("%H:%M").to_string()), ("day", day)],
+    );
+
+    bot.send_message(msg.chat.id, text)
+        .parse_mode(ParseMode::Html)
+        .await?;
+
+    Ok(())
+}
+
+pub async fn unsubscribe_command_handler(
+    storage: &dyn Storage,
+    bot: &Bot,
+    msg: &Message,
+    user_entry: &UserEntry,
+    lang: Language,
+) -> Result<()> {
+    let key = if storage.delete_digest_subscription(user_entry.id).await? {
+        "unsubscribed"
+    } else {
+        "not-subscribed"
+    };
+
+    bot.send_message(msg.chat.id, tr(lang, key)).await?;
+
+    Ok(())
+}
+
+pub async fn remind_command_handler(
+    storage: &dyn Storage,
+    bot: &Bot,
+    msg: &Message,
+    user_entry: &UserEntry,
+    args: &str,
+    lang: Language,
+) -> Result<()> {
+    let args = args.trim().to_lowercase();
+
+    if matches!(args.as_str(), "off" | "выкл") {
+        let key = if storage.delete_reminder_setting(user_entry.id).await? {
+            "reminders-off"
+        } else {
+            "reminders-not-enabled"
+        };
+
+        bot.send_message(msg.chat.id, tr(lang, key)).await?;
+        return Ok(());
+    }
+
+    let lead_minutes = match args.as_str() {
+        "" => Some(DEFAULT_LEAD_MINUTES),
+        value => value
+            .parse::<i16>()
+            .ok()
+            .filter(|value| (1..=MAX_LEAD_MINUTES).contains(value)),
+    };
+
+    let Some(lead_minutes) = lead_minutes else {
+        bot.send_message(
+            msg.chat.id,
+            tr_args(
+                lang,
+                "remind-invalid",
+                &[("max", &MAX_LEAD_MINUTES.to_string())],
+            ),
+        )
+        .await?;
+        return Ok(());
+    };
+
+    storage
+        .upsert_reminder_setting(user_entry.id, lead_minutes)
+        .await?;
+
+    let text = tr_args(
+        lang,
+        "reminders-on",
+        &[("minutes", &lead_minutes.to_string())],
+    );
+
+    bot.send_message(msg.chat.id, text)
+        .parse_mode(ParseMode::Html)
+        .await?;
+
+    Ok(())
+}
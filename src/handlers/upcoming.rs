@@ -0,0 +1,168 @@
+use anyhow::Result;
+use chrono::{DateTime, Duration, FixedOffset, NaiveTime};
+use teloxide::{prelude::*, types::ParseMode};
+
+use crate::utils::{
+    i18n::{tr, tr_args, Language},
+    sql::types::TimeTableEntry,
+    storage::Storage,
+    time::{add_days, local_datetime},
+};
+
+use super::schedule::{find_timetable, format_entry, TimetableScope};
+
+/// How far ahead the next lesson is looked for, covers holidays and breaks between weeks.
+const MAX_LOOKAHEAD_DAYS: i64 = 14;
+
+/// Renders the duration like `1 h 5 min`, partial minutes are rounded up.
+fn format_remaining(duration: Duration, lang: Language) -> String {
+    let minutes = (duration.num_seconds() + 59) / 60;
+    let (days, hours, minutes) = (minutes / (24 * 60), minutes / 60 % 24, minutes % 60);
+
+    let parts = [
+        (days, "duration-days"),
+        (hours, "duration-hours"),
+        (minutes, "duration-minutes"),
+    ]
+    .into_iter()
+    .filter(|(value, _)| *value > 0)
+    .map(|(value, key)| tr_args(lang, key, &[("value", &value.to_string())]))
+    .collect::<Vec<_>>();
+
+    if parts.is_empty() {
+        tr(lang, "duration-less-than-minute").to_owned()
+    } else {
+        parts.join(" ")
+    }
+}
+
+fn lesson_time(day: &DateTime<FixedOffset>, time: NaiveTime) -> Option<DateTime<FixedOffset>> {
+    local_datetime(day.date_naive(), time).ok()
+}
+
+/// The lesson which is going on right now.
+async fn find_current_lesson(
+    storage: &dyn Storage,
+    dt: &DateTime<FixedOffset>,
+    scope: &TimetableScope,
+) -> Result<Option<TimeTableEntry>> {
+    let time = dt.time();
+    let entries = find_timetable(storage, dt, scope).await?;
+
+    Ok(entries
+        .into_iter()
+        .find(|entry| entry.starts_at <= time && time < entry.ends_at))
+}
+
+/// The first lesson which starts after `dt`, today or on one of the following study days.
+async fn find_next_lesson(
+    storage: &dyn Storage,
+    dt: &DateTime<FixedOffset>,
+    scope: &TimetableScope,
+) -> Result<Option<(DateTime<FixedOffset>, TimeTableEntry)>> {
+    for offset in 0..=MAX_LOOKAHEAD_DAYS {
+        let day = add_days(dt, offset)?;
+        let entries = find_timetable(storage, &day, scope).await?;
+
+        let next = entries
+            .into_iter()
+            .filter_map(|entry| Some((lesson_time(&day, entry.starts_at)?, entry)))
+            .find(|(starts_at, _)| starts_at > dt);
+
+        if next.is_some() {
+            return Ok(next);
+        }
+    }
+
+    Ok(None)
+}
+
+async fn describe_next_lesson(
+    storage: &dyn Storage,
+    dt: &DateTime<FixedOffset>,
+    scope: &TimetableScope,
+    lang: Language,
+) -> Result<String> {
+    let Some((starts_at, entry)) = find_next_lesson(storage, dt, scope).await? else {
+        let days = MAX_LOOKAHEAD_DAYS.to_string();
+        return Ok(format!(
+            "<i>{}</i>",
+            tr_args(lang, "next-none", &[("days", &days)])
+        ));
+    };
+
+    let when = if starts_at.date_naive() == dt.date_naive() {
+        tr(lang, "next-today").to_owned()
+    } else {
+        starts_at
+            .format_localized("%A, %e %B", lang.locale())
+            .to_string()
+    };
+
+    let title = tr_args(
+        lang,
+        "next-title",
+        &[("remaining", &format_remaining(starts_at - *dt, lang))],
+    );
+
+    Ok(format!(
+        "<b>{title}</b> ({when})\n\n{}",
+        format_entry(&entry, lang)?
+    ))
+}
+
+pub async fn next_command_handler(
+    storage: &dyn Storage,
+    bot: &Bot,
+    msg: &Message,
+    dt: DateTime<FixedOffset>,
+    scope: &TimetableScope,
+    lang: Language,
+) -> Result<()> {
+    let text = describe_next_lesson(storage, &dt, scope, lang).await?;
+
+    bot.send_message(msg.chat.id, text)
+        .parse_mode(ParseMode::Html)
+        .await?;
+
+    Ok(())
+}
+
+pub async fn now_command_handler(
+    storage: &dyn Storage,
+    bot: &Bot,
+    msg: &Message,
+    dt: DateTime<FixedOffset>,
+    scope: &TimetableScope,
+    lang: Language,
+) -> Result<()> {
+    let text = match find_current_lesson(storage, &dt, scope).await? {
+        Some(entry) => {
+            let remaining = match lesson_time(&dt, entry.ends_at) {
+                Some(ends_at) => tr_args(
+                    lang,
+                    "now-remaining",
+                    &[("remaining", &format_remaining(ends_at - dt, lang))],
+                ),
+                None => String::new(),
+            };
+
+            format!(
+                "<b>{}</b>{remaining}\n\n{}",
+                tr(lang, "now-title"),
+                format_entry(&entry, lang)?
+            )
+        }
+        None => format!(
+            "<i>{}</i>\n\n{}",
+            tr(lang, "now-none"),
+            describe_next_lesson(storage, &dt, scope, lang).await?
+        ),
+    };
+
+    bot.send_message(msg.chat.id, text)
+        .parse_mode(ParseMode::Html)
+        .await?;
+
+    Ok(())
+}
@@ -1,30 +1,82 @@
-use std::sync::Arc;
+use std::{path::PathBuf, sync::Arc};
 
-use anyhow::Result;
+use anyhow::{anyhow, Result};
 use teloxide::{prelude::*, types::Update};
 
 use crate::button_prefix::ButtonPrefix;
 use crate::config::AppConfig;
+use crate::handlers::admin::PendingChange;
+use crate::tasks::notifier::Notifier;
 use crate::utils::database::Database;
+use crate::utils::pending::Pending;
+use crate::utils::storage::{postgres::PgStorage, Storage};
 
 mod button_prefix;
 mod config;
 mod handlers;
+mod tasks;
 mod utils;
 
+/// Applies migrations and exits without starting the bot, meant for deployments.
+const MIGRATE_ONLY_FLAG: &str = "--migrate-only";
+
+/// Path of the config file, `--config <path>` or `--config=<path>`.
+const CONFIG_FLAG: &str = "--config";
+
+fn config_path() -> Result<Option<PathBuf>> {
+    let mut args = std::env::args().skip(1);
+
+    while let Some(arg) = args.next() {
+        if arg == CONFIG_FLAG {
+            let path = args
+                .next()
+                .ok_or(anyhow!("{CONFIG_FLAG} requires a path"))?;
+            return Ok(Some(path.into()));
+        }
+
+        if let Some(path) = arg
+            .strip_prefix(CONFIG_FLAG)
+            .and_then(|arg| arg.strip_prefix('='))
+        {
+            return Ok(Some(path.into()));
+        }
+    }
+
+    Ok(None)
+}
+
 #[tokio::main]
 async fn main() -> Result<()> {
     simple_logger::init_with_env().unwrap();
 
     log::info!("Starting...");
 
-    let config = Arc::new(AppConfig::figment());
+    let config = Arc::new(AppConfig::load(config_path()?.as_deref())?);
+    utils::time::init_timezone(config.institution.timezone)?;
+    log::info!("Using the {} timezone", config.institution.timezone);
 
     let pool = Database::create_pool(&config.database.url).await?;
     let db = Database::new(Arc::new(pool));
 
+    log::info!("Applying database migrations...");
+    db.migrate().await?;
+
+    if std::env::args().any(|arg| arg == MIGRATE_ONLY_FLAG) {
+        log::info!("Migrations are applied, exiting");
+        return Ok(());
+    }
+
+    let storage: Arc<dyn Storage> = Arc::new(PgStorage::new(db));
+    let pending_changes: Pending<PendingChange> = Pending::new();
+
     let bot = Bot::new(&config.telegram.token);
 
+    tokio::spawn(tasks::digest::run(bot.clone(), storage.clone()));
+    tokio::spawn(tasks::reminders::run(bot.clone(), storage.clone()));
+
+    let (notifier, notices) = Notifier::new();
+    tokio::spawn(tasks::notifier::run(bot.clone(), storage.clone(), notices));
+
     let commands_handler = Update::filter_message()
         .branch(
             dptree::entry()
@@ -33,24 +85,26 @@ async fn main() -> Result<()> {
         )
         .branch(
             dptree::entry()
-                .filter_async(|bot: Bot, msg: Message, db: Database| async move {
-                    handlers::schedule::filter_predicate(&bot, &msg, &db)
-                        .await
-                        .unwrap()
-                })
                 .filter_command::<handlers::TimetableCommand>()
                 .endpoint(handlers::timetable_commands_handler),
         )
         .branch(
             dptree::filter(|cfg: Arc<AppConfig>, msg: Message| {
                 if let Some(sender) = msg.from() {
-                    cfg.telegram.owner_ids.contains(&sender.id.0)
+                    cfg.telegram.is_owner(sender.id.0)
                 } else {
                     false
                 }
             })
-            .filter_command::<handlers::admin::AdminCommand>()
-            .endpoint(handlers::admin::commands_handler),
+            .branch(
+                dptree::entry()
+                    .filter_command::<handlers::admin::AdminCommand>()
+                    .endpoint(handlers::admin::commands_handler),
+            )
+            .branch(
+                dptree::filter(|msg: Message| msg.document().is_some())
+                    .endpoint(handlers::admin::import_document_handler),
+            ),
         );
 
     let callback_handler = Update::filter_callback_query()
@@ -61,20 +115,88 @@ async fn main() -> Result<()> {
             })
             .endpoint(handlers::general::set_major_callback_handler),
         )
+        .branch(
+            dptree::filter(|q: CallbackQuery| match q.data {
+                Some(data) => data.starts_with(&format!("{}", ButtonPrefix::SetChatMajor)),
+                None => false,
+            })
+            .endpoint(handlers::general::set_chat_major_callback_handler),
+        )
+        .branch(
+            dptree::filter(|q: CallbackQuery| match q.data {
+                Some(data) => data.starts_with(&format!("{}", ButtonPrefix::SetSubgroup)),
+                None => false,
+            })
+            .endpoint(handlers::general::set_subgroup_callback_handler),
+        )
+        .branch(
+            dptree::filter(|q: CallbackQuery| match q.data {
+                Some(data) => data.starts_with(&format!("{}", ButtonPrefix::SetLanguage)),
+                None => false,
+            })
+            .endpoint(handlers::general::set_language_callback_handler),
+        )
+        .branch(
+            dptree::filter(|q: CallbackQuery| match q.data {
+                Some(data) => data.starts_with(&format!("{}", ButtonPrefix::ElectiveMajor)),
+                None => false,
+            })
+            .endpoint(handlers::electives::elective_major_callback_handler),
+        )
+        .branch(
+            dptree::filter(|q: CallbackQuery| match q.data {
+                Some(data) => data.starts_with(&format!("{}", ButtonPrefix::ToggleElective)),
+                None => false,
+            })
+            .endpoint(handlers::electives::toggle_elective_callback_handler),
+        )
         .branch(
             dptree::filter(|q: CallbackQuery| match q.data {
                 Some(data) => data.starts_with(&format!("{}", ButtonPrefix::TimetableWeekday)),
                 None => false,
             })
             .endpoint(handlers::timetable_callback_handler),
+        )
+        .branch(
+            dptree::filter(|q: CallbackQuery| match q.data {
+                Some(data) => data.starts_with(&format!("{}:", ButtonPrefix::TimetableWeek)),
+                None => false,
+            })
+            .endpoint(handlers::week_callback_handler),
+        )
+        .branch(
+            dptree::filter(|q: CallbackQuery| match q.data {
+                Some(data) => data.starts_with(&format!("{}", ButtonPrefix::ProfessorWeekday)),
+                None => false,
+            })
+            .endpoint(handlers::professor::professor_callback_handler),
+        )
+        .branch(
+            dptree::filter(|cfg: Arc<AppConfig>, q: CallbackQuery| {
+                let is_owner = cfg.telegram.is_owner(q.from.id.0);
+
+                match q.data {
+                    Some(data) => {
+                        is_owner
+                            && (data.starts_with(&format!("{}:", ButtonPrefix::AdminConfirm))
+                                || data.starts_with(&format!("{}:", ButtonPrefix::AdminCancel)))
+                    }
+                    None => false,
+                }
+            })
+            .endpoint(handlers::admin::change_callback_handler),
         );
 
-    let handler = dptree::entry()
+    let inline_handler =
+        Update::filter_inline_query().endpoint(handlers::inline::inline_query_handler);
+
+    let handler = handlers::error::report_errors()
         .branch(commands_handler)
-        .branch(callback_handler);
+        .branch(callback_handler)
+        .branch(inline_handler);
 
     Dispatcher::builder(bot, handler)
-        .dependencies(dptree::deps![config, db])
+        .dependencies(dptree::deps![config, storage, pending_changes, notifier])
         .default_handler(|upd| async move {
             log::warn!("Unhandled update: #{}", upd.id);
         })
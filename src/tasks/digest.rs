@@ -0,0 +1,80 @@
+use std::{sync::Arc, time::Duration as StdDuration};
+
+use anyhow::Result;
+use chrono::{DateTime, FixedOffset};
+use teloxide::{prelude::*, types::ParseMode};
+
+use crate::{
+    handlers::schedule::{prepate_text, TimetableScope},
+    utils::{sql::types::DueDigest, storage::Storage, time::add_days},
+};
+
+use super::SEND_DELAY;
+
+const TICK: StdDuration = StdDuration::from_secs(60);
+
+async fn prepare_digest(
+    storage: &dyn Storage,
+    digest: DueDigest,
+    dt: &DateTime<FixedOffset>,
+) -> Result<String> {
+    let day = if digest.next_day {
+        add_days(dt, 1)?
+    } else {
+        *dt
+    };
+
+    let electives = storage.get_electives(digest.user_id).await?;
+    let scope = TimetableScope::new(digest.major_id, digest.subgroup, electives);
+
+    prepate_text(storage, &day, &scope, digest.language).await
+}
+
+async fn send_due_digests(bot: &Bot, storage: &dyn Storage) -> Result<()> {
+    let dt = crate::utils::time::now()?;
+    let today = dt.date_naive();
+
+    let digests = storage.get_due_digests(today, dt.time()).await?;
+
+    for digest in digests {
+        let user_id = digest.user_id;
+
+        // mark it first, so that a failed digest is not retried every minute
+        storage.mark_digest_sent(user_id, today).await?;
+
+        // a failure of one user's digest must not keep the others from theirs
+        let text = match prepare_digest(storage, digest, &dt).await {
+            Ok(text) => text,
+            Err(err) => {
+                log::warn!("Failed to prepare a digest for #{user_id}: {err}");
+                continue;
+            }
+        };
+
+        let result = bot
+            .send_message(ChatId(user_id), text)
+            .parse_mode(ParseMode::Html)
+            .await;
+
+        if let Err(err) = result {
+            log::warn!("Failed to send a digest to #{user_id}: {err}");
+        }
+
+        tokio::time::sleep(SEND_DELAY).await;
+    }
+
+    Ok(())
+}
+
+/// Sends daily digests to subscribed users, runs forever.
+pub async fn run(bot: Bot, storage: Arc<dyn Storage>) {
+    let mut interval = tokio::time::interval(TICK);
+
+    loop {
+        interval.tick().await;
+
+        if let Err(err) = send_due_digests(&bot, storage.as_ref()).await {
+            log::error!("Failed to send daily digests: {err}");
+        }
+    }
+}
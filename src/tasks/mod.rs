@@ -0,0 +1,8 @@
+pub mod digest;
+pub mod reminders;
+pub mod notifier;
+
+use std::time::Duration;
+
+/// Pause between two messages, keeps the bot below Telegram's broadcast limits.
+pub(crate) const SEND_DELAY: Duration = Duration::from_millis(50);
@@ -0,0 +1,127 @@
+use std::{
+    collections::{hash_map::Entry, HashMap},
+    fmt,
+    sync::Arc,
+};
+
+use anyhow::Result;
+use teloxide::{prelude::*, types::ParseMode, RequestError};
+use tokio::sync::mpsc;
+
+use crate::utils::{
+    i18n::{tr, Language},
+    storage::Storage,
+};
+
+use super::SEND_DELAY;
+
+type RenderNotice = Arc<dyn Fn(Language) -> Result<String> + Send + Sync>;
+
+/// A message about a timetable change, `major_id` is `None` if every student is affected.
+#[derive(Clone)]
+pub struct ChangeNotice {
+    pub major_id: Option<String>,
+    render: RenderNotice,
+}
+
+impl ChangeNotice {
+    /// The message in the language of the recipient.
+    pub fn render(&self, lang: Language) -> Result<String> {
+        Ok(format!(
+            "<b>{}</b>\n\n{}",
+            tr(lang, "notice-title"),
+            (self.render)(lang)?
+        ))
+    }
+}
+
+impl fmt::Debug for ChangeNotice {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ChangeNotice")
+            .field("major_id", &self.major_id)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Queues notices about timetable changes, they are sent by [`run`] in the background.
+#[derive(Debug, Clone)]
+pub struct Notifier {
+    sender: mpsc::UnboundedSender<ChangeNotice>,
+}
+
+impl Notifier {
+    pub fn new() -> (Self, mpsc::UnboundedReceiver<ChangeNotice>) {
+        let (sender, receiver) = mpsc::unbounded_channel();
+        (Self { sender }, receiver)
+    }
+
+    /// Queues a notice, `render` builds its text for every language of the recipients.
+    pub fn notify(
+        &self,
+        major_id: Option<&str>,
+        render: impl Fn(Language) -> Result<String> + Send + Sync + 'static,
+    ) {
+        let notice = ChangeNotice {
+            major_id: major_id.map(str::to_owned),
+            render: Arc::new(render),
+        };
+
+        if self.sender.send(notice).is_err() {
+            log::error!("Failed to queue a timetable change notice, the notifier is stopped");
+        }
+    }
+}
+
+async fn send_with_retry(bot: &Bot, chat_id: ChatId, text: &str) -> Result<(), RequestError> {
+    let send = || {
+        bot.send_message(chat_id, text)
+            .parse_mode(ParseMode::Html)
+            .send()
+    };
+
+    match send().await {
+        Err(RequestError::RetryAfter(duration)) => {
+            tokio::time::sleep(duration).await;
+            send().await.map(|_| ())
+        }
+        result => result.map(|_| ()),
+    }
+}
+
+async fn broadcast(bot: &Bot, storage: &dyn Storage, notice: &ChangeNotice) -> Result<()> {
+    let user_ids = storage
+        .get_user_ids_by_major(notice.major_id.as_deref())
+        .await?;
+
+    // the notice is rendered once per language
+    let mut texts: HashMap<Language, String> = HashMap::new();
+
+    for user_id in user_ids {
+        let lang = storage.get_user_language(user_id).await?;
+        let text = match texts.entry(lang) {
+            Entry::Occupied(entry) => entry.into_mut(),
+            Entry::Vacant(entry) => entry.insert(notice.render(lang)?),
+        };
+
+        if let Err(err) = send_with_retry(bot, ChatId(user_id), text).await {
+            log::warn!("Failed to notify #{user_id} about a timetable change: {err}");
+        }
+
+        tokio::time::sleep(SEND_DELAY).await;
+    }
+
+    Ok(())
+}
+
+/// Sends queued notices to the affected students, runs until every [`Notifier`] is dropped.
+pub async fn run(
+    bot: Bot,
+    storage: Arc<dyn Storage>,
+    mut receiver: mpsc::UnboundedReceiver<ChangeNotice>,
+) {
+    while let Some(notice) = receiver.recv().await {
+        if let Err(err) = broadcast(&bot, storage.as_ref(), &notice).await {
+            log::error!("Failed to send a timetable change notice: {err}");
+        }
+    }
+}
@@ -0,0 +1,139 @@
+use std::{collections::HashMap, sync::Arc, time::Duration as StdDuration};
+
+use anyhow::Result;
+use chrono::{Duration, NaiveDate};
+use teloxide::{prelude::*, types::ParseMode};
+
+use crate::{
+    handlers::schedule::{find_timetable, TimetableScope},
+    utils::{
+        i18n::tr_args,
+        sql::types::{ElectiveEntry, TimeTableEntry},
+        storage::Storage,
+        time::add_days,
+    },
+};
+
+use super::SEND_DELAY;
+
+const TICK: StdDuration = StdDuration::from_secs(30);
+
+/// For how many days sent reminders are remembered.
+const SENT_RETENTION_DAYS: i64 = 2;
+
+async fn send_due_reminders(bot: &Bot, storage: &dyn Storage) -> Result<()> {
+    let dt = crate::utils::time::now()?;
+    let now = dt.naive_local();
+    let today = dt.date_naive();
+    // lessons shortly after midnight are reminded about on the previous day
+    let tomorrow = add_days(&dt, 1)?;
+
+    let mut electives_by_user: HashMap<i64, Vec<ElectiveEntry>> = HashMap::new();
+    for elective in storage.get_reminded_electives().await? {
+        electives_by_user
+            .entry(elective.user_id)
+            .or_default()
+            .push(elective);
+    }
+
+    // overrides are taken into account, so cancelled lessons are not reminded about,
+    // every major is loaded once per day and each user is reminded only about their own lessons
+    let mut timetables: HashMap<(NaiveDate, String), Vec<TimeTableEntry>> = HashMap::new();
+
+    for setting in storage.get_reminder_settings().await? {
+        let lead = Duration::minutes(setting.lead_minutes.into());
+        let lang = setting.language;
+        let electives = electives_by_user
+            .remove(&setting.user_id)
+            .unwrap_or_default();
+        let scope = TimetableScope::new(setting.major_id, setting.subgroup, electives);
+
+        let days = if (now + lead).date() > today {
+            vec![dt, tomorrow]
+        } else {
+            vec![dt]
+        };
+
+        let mut due = vec![];
+
+        for day in &days {
+            let date = day.date_naive();
+
+            for major_id in scope.majors() {
+                let key = (date, major_id.to_owned());
+
+                if !timetables.contains_key(&key) {
+                    let entries =
+                        find_timetable(storage, day, &TimetableScope::major(major_id)).await?;
+                    timetables.insert(key.clone(), entries);
+                }
+
+                due.extend(
+                    timetables[&key]
+                        .iter()
+                        .filter(|entry| scope.includes(major_id, entry))
+                        .map(|entry| (date.and_time(entry.starts_at), entry.clone()))
+                        .filter(|(starts_at, _)| *starts_at > now && *starts_at - now <= lead),
+                );
+            }
+        }
+
+        for (starts_at, entry) in due {
+            let is_new = storage
+                .mark_reminder_sent(
+                    setting.user_id,
+                    starts_at.date(),
+                    entry.starts_at,
+                    entry.lesson_id(),
+                )
+                .await?;
+
+            if !is_new {
+                continue;
+            }
+
+            let seconds_left = (starts_at - now).num_seconds();
+            let minutes_left = (seconds_left + 59) / 60;
+
+            let text = tr_args(
+                lang,
+                "reminder",
+                &[
+                    ("minutes", &minutes_left.to_string()),
+                    ("subject", &entry.subject_name),
+                    ("auditorium", &entry.auditorium),
+                ],
+            );
+
+            let result = bot
+                .send_message(ChatId(setting.user_id), text)
+                .parse_mode(ParseMode::Html)
+                .await;
+
+            if let Err(err) = result {
+                log::warn!("Failed to send a reminder to #{}: {err}", setting.user_id);
+            }
+
+            tokio::time::sleep(SEND_DELAY).await;
+        }
+    }
+
+    storage
+        .delete_sent_reminders_before(today - Duration::days(SENT_RETENTION_DAYS))
+        .await?;
+
+    Ok(())
+}
+
+/// Reminds users about lessons which are about to start, runs forever.
+pub async fn run(bot: Bot, storage: Arc<dyn Storage>) {
+    let mut interval = tokio::time::interval(TICK);
+
+    loop {
+        interval.tick().await;
+
+        if let Err(err) = send_due_reminders(&bot, storage.as_ref()).await {
+            log::error!("Failed to send reminders: {err}");
+        }
+    }
+}
@@ -21,4 +21,11 @@ impl Database {
 
         Ok(pool)
     }
+
+    /// Applies the migrations from `migrations/` which are not applied yet.
+    pub async fn migrate(&self) -> Result<()> {
+        sqlx::migrate!().run(self.pool.as_ref()).await?;
+
+        Ok(())
+    }
 }
@@ -0,0 +1,92 @@
+use anyhow::Result;
+
+use super::{
+    sql::types::{LessonDurationEntry, DEFAULT_LESSON_DURATION_MINUTES},
+    storage::Storage,
+};
+
+/// Configured default durations of new lessons.
+#[derive(Debug, Clone, Default)]
+pub struct LessonDurations {
+    defaults: Vec<LessonDurationEntry>,
+}
+
+impl LessonDurations {
+    pub fn new(defaults: Vec<LessonDurationEntry>) -> Self {
+        Self { defaults }
+    }
+
+    pub async fn load(storage: &dyn Storage) -> Result<Self> {
+        let defaults = storage.get_lesson_durations().await?;
+        Ok(Self::new(defaults))
+    }
+
+    /// The most specific default: major and subject type, then the subject type,
+    /// then the major, then the global fallback.
+    pub fn minutes_for(&self, major_id: &str, subject_type: &str) -> i16 {
+        self.defaults
+            .iter()
+            .filter(|entry| entry.matches(major_id, subject_type))
+            .min_by_key(|entry| (entry.subject_type.is_none(), entry.major_id.is_none()))
+            .map_or(DEFAULT_LESSON_DURATION_MINUTES, |entry| {
+                entry.duration_minutes
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn default(
+        id: i64,
+        major_id: Option<&str>,
+        subject_type: Option<&str>,
+        minutes: i16,
+    ) -> LessonDurationEntry {
+        LessonDurationEntry {
+            id,
+            major_id: major_id.map(str::to_owned),
+            subject_type: subject_type.map(str::to_owned),
+            duration_minutes: minutes,
+        }
+    }
+
+    #[test]
+    fn most_specific_default_wins() {
+        let all = [
+            default(1, Some("ivt"), Some("Лабораторная"), 180),
+            default(2, None, Some("Лабораторная"), 135),
+            default(3, Some("ivt"), None, 80),
+            default(4, None, None, 60),
+        ];
+
+        // configured defaults, major, subject type, expected minutes
+        let cases: &[(&[i64], &str, &str, i16)] = &[
+            (&[1, 2, 3, 4], "ivt", "Лабораторная", 180),
+            (&[4, 3, 2, 1], "ivt", "Лабораторная", 180),
+            (&[1, 2, 3, 4], "pm", "Лабораторная", 135),
+            (&[2, 3, 4], "ivt", "Лабораторная", 135),
+            (&[1, 2, 3, 4], "ivt", "Лекция", 80),
+            (&[3, 4], "ivt", "Лабораторная", 80),
+            (&[1, 2, 3, 4], "pm", "Лекция", 60),
+            (&[4], "ivt", "Лабораторная", 60),
+            (&[1, 2, 3], "pm", "Лекция", DEFAULT_LESSON_DURATION_MINUTES),
+            (&[], "ivt", "Лекция", DEFAULT_LESSON_DURATION_MINUTES),
+        ];
+
+        for &(ids, major_id, subject_type, expected) in cases {
+            let defaults = ids
+                .iter()
+                .map(|id| all.iter().find(|entry| entry.id == *id).unwrap().clone())
+                .collect();
+            let durations = LessonDurations::new(defaults);
+
+            assert_eq!(
+                durations.minutes_for(major_id, subject_type),
+                expected,
+                "{ids:?} {major_id} {subject_type}"
+            );
+        }
+    }
+}
@@ -0,0 +1,153 @@
+/// Lowercases the value, replaces `ё` and drops punctuation, so `Иванов И.И.` becomes `иванов и и`.
+pub fn normalize(value: &str) -> String {
+    value
+        .to_lowercase()
+        .replace('ё', "е")
+        .chars()
+        .map(|c| if c.is_alphanumeric() { c } else { ' ' })
+        .collect()
+}
+
+fn levenshtein(a: &str, b: &str) -> usize {
+    let b = b.chars().collect::<Vec<_>>();
+    let mut previous = (0..=b.len()).collect::<Vec<_>>();
+
+    for (i, ca) in a.chars().enumerate() {
+        let mut current = vec![i + 1];
+
+        for (j, cb) in b.iter().enumerate() {
+            let cost = usize::from(ca != *cb);
+            current.push(
+                (previous[j] + cost)
+                    .min(previous[j + 1] + 1)
+                    .min(current[j] + 1),
+            );
+        }
+
+        previous = current;
+    }
+
+    previous[b.len()]
+}
+
+/// How far a word of the query is from a word of the candidate, `None` if they are unrelated.
+///
+/// A prefix is an exact match, so `иван` finds `Иванов`, a few typos are tolerated.
+fn word_distance(query: &str, word: &str) -> Option<usize> {
+    if word.starts_with(query) {
+        return Some(0);
+    }
+
+    let allowed = (query.chars().count() / 4).max(1);
+    let prefix = word.chars().take(query.chars().count()).collect::<String>();
+
+    [levenshtein(query, word), levenshtein(query, &prefix)]
+        .into_iter()
+        .min()
+        .filter(|distance| *distance <= allowed)
+}
+
+/// Sum of distances between every word of the query and its closest word of the candidate,
+/// `None` if some word of the query matches nothing.
+pub fn match_score(query: &str, candidate: &str) -> Option<usize> {
+    let query = normalize(query);
+    let candidate = normalize(candidate);
+    let words = candidate.split_whitespace().collect::<Vec<_>>();
+
+    let mut score = 0;
+    let mut matched = false;
+
+    for part in query.split_whitespace() {
+        score += words
+            .iter()
+            .filter_map(|word| word_distance(part, word))
+            .min()?;
+        matched = true;
+    }
+
+    matched.then_some(score)
+}
+
+/// Candidates which match the query best, in their original order.
+pub fn best_matches<'a, T>(
+    query: &str,
+    candidates: &'a [T],
+    name: impl Fn(&T) -> &str,
+) -> Vec<&'a T> {
+    let scored = candidates
+        .iter()
+        .filter_map(|candidate| Some((match_score(query, name(candidate))?, candidate)))
+        .collect::<Vec<_>>();
+
+    let Some(best) = scored.iter().map(|(score, _)| *score).min() else {
+        return vec![];
+    };
+
+    scored
+        .into_iter()
+        .filter(|(score, _)| *score == best)
+        .map(|(_, candidate)| candidate)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PROFESSORS: [&str; 5] = [
+        "Иванов Иван Иванович",
+        "Иванова Мария Петровна",
+        "Петров Пётр Сергеевич",
+        "Семёнов А.А.",
+        "Smith John",
+    ];
+
+    #[test]
+    fn best_matches_of_the_query() {
+        let cases: &[(&str, &[&str])] = &[
+            // ranking, a prefix counts as an exact word and beats a typo, so `иванов` can't
+            // single out `Иванов` from `Иванова`, ties keep the original order
+            ("иванова", &["Иванова Мария Петровна"]),
+            (
+                "иванов",
+                &["Иванов Иван Иванович", "Иванова Мария Петровна"],
+            ),
+            ("петр", &["Иванова Мария Петровна", "Петров Пётр Сергеевич"]),
+            ("иванов мария", &["Иванова Мария Петровна"]),
+            ("петров сергеевич", &["Петров Пётр Сергеевич"]),
+            // typos within the allowed distance
+            (
+                "ивонов",
+                &["Иванов Иван Иванович", "Иванова Мария Петровна"],
+            ),
+            ("сиргеевеч", &["Петров Пётр Сергеевич"]),
+            // cut-off, too many typos for the length of the word
+            ("ивнаов", &[]),
+            ("сиргиевеч", &[]),
+            ("сидоров", &[]),
+            ("иванов сидоров", &[]),
+            // case, `ё` and punctuation
+            ("ИВАНОВА", &["Иванова Мария Петровна"]),
+            ("семенов", &["Семёнов А.А."]),
+            ("Семёнов А.А.", &["Семёнов А.А."]),
+            ("пётр", &["Иванова Мария Петровна", "Петров Пётр Сергеевич"]),
+            ("sMiTh", &["Smith John"]),
+            // nothing to look for
+            ("", &[]),
+            (" .,. ", &[]),
+        ];
+
+        for (query, expected) in cases {
+            let matches = best_matches(query, &PROFESSORS, |name| name);
+            let matches = matches.into_iter().copied().collect::<Vec<_>>();
+
+            assert_eq!(&matches, expected, "{query:?}");
+        }
+    }
+
+    #[test]
+    fn words_are_normalized() {
+        assert_eq!(normalize("Семёнов А.А."), "семенов а а ");
+        assert_eq!(normalize("ЁЖ-ИК"), "еж ик");
+    }
+}
@@ -0,0 +1,84 @@
+use std::collections::HashMap;
+
+use once_cell::sync::Lazy;
+use serde::Deserialize;
+use strum::EnumString;
+
+/// Language of the interface, stored per user in `users.language`.
+#[derive(
+    Debug,
+    Clone,
+    Copy,
+    Default,
+    PartialEq,
+    Eq,
+    Hash,
+    sqlx::Type,
+    Deserialize,
+    EnumString,
+    strum::Display,
+)]
+#[sqlx(type_name = "language_code", rename_all = "lowercase")]
+#[serde(rename_all = "lowercase")]
+#[strum(serialize_all = "lowercase")]
+pub enum Language {
+    #[default]
+    Ru,
+    En,
+}
+
+impl Language {
+    pub const ALL: [Language; 2] = [Language::Ru, Language::En];
+
+    /// Locale for weekday and month names.
+    pub fn locale(self) -> chrono::Locale {
+        match self {
+            Language::Ru => chrono::Locale::ru_RU,
+            Language::En => chrono::Locale::en_US,
+        }
+    }
+
+    /// Name of the language in the language itself.
+    pub fn native_name(self) -> &'static str {
+        match self {
+            Language::Ru => "Русский",
+            Language::En => "English",
+        }
+    }
+
+    fn catalogue(self) -> &'static HashMap<String, String> {
+        match self {
+            Language::Ru => &RU,
+            Language::En => &EN,
+        }
+    }
+}
+
+type Catalogue = HashMap<String, String>;
+
+fn parse_catalogue(source: &str) -> Catalogue {
+    toml::from_str(source).expect("message catalogue must be valid TOML")
+}
+
+static RU: Lazy<Catalogue> = Lazy::new(|| parse_catalogue(include_str!("../../locales/ru.toml")));
+static EN: Lazy<Catalogue> = Lazy::new(|| parse_catalogue(include_str!("../../locales/en.toml")));
+
+/// Message from the catalogue, Russian is used if the language lacks it.
+pub fn tr(lang: Language, key: &str) -> &'static str {
+    lang.catalogue()
+        .get(key)
+        .or_else(|| Language::default().catalogue().get(key))
+        .map(String::as_str)
+        .unwrap_or_else(|| {
+            log::warn!("Missing message {key:?}");
+            ""
+        })
+}
+
+/// Same as `tr`, `{name}` placeholders are replaced with the arguments.
+pub fn tr_args(lang: Language, key: &str, args: &[(&str, &str)]) -> String {
+    args.iter()
+        .fold(tr(lang, key).to_owned(), |message, (name, value)| {
+            message.replace(&format!("{{{name}}}"), value)
+        })
+}
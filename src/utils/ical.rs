@@ -0,0 +1,365 @@
+use anyhow::{anyhow, Result};
+use chrono::{
+    DateTime, Duration, FixedOffset, NaiveDate, NaiveTime, Offset, TimeZone, Utc, Weekday,
+};
+
+use chrono_tz::{OffsetComponents, OffsetName, Tz};
+
+use super::{
+    semester::SemesterCalendar,
+    sql::types::TimeTableEntry,
+    time::{local_datetime, localize, timezone, Week},
+};
+
+const DATE_TIME_FORMAT: &str = "%Y%m%dT%H%M%SZ";
+const LOCAL_DATE_TIME_FORMAT: &str = "%Y%m%dT%H%M%S";
+
+/// Lines of iCalendar content must not be longer than 75 octets.
+const MAX_LINE_LENGTH: usize = 75;
+
+/// Escapes a TEXT value according to RFC 5545, section 3.3.11.
+fn escape_text(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace(';', "\\;")
+        .replace(',', "\\,")
+        .replace('\n', "\\n")
+}
+
+/// Splits a content line into several ones, each continuation starts with a space.
+fn fold_line(line: &str) -> String {
+    let mut folded = String::with_capacity(line.len());
+    let mut length = 0;
+
+    for c in line.chars() {
+        if length + c.len_utf8() > MAX_LINE_LENGTH {
+            folded.push_str("\r\n ");
+            length = 1;
+        }
+
+        folded.push(c);
+        length += c.len_utf8();
+    }
+
+    folded
+}
+
+fn format_utc(dt: &DateTime<FixedOffset>) -> String {
+    dt.with_timezone(&Utc).format(DATE_TIME_FORMAT).to_string()
+}
+
+/// A property value with the local time and the institution timezone, so that recurring
+/// events keep their local time across DST transitions.
+fn format_local(dt: &DateTime<FixedOffset>) -> String {
+    format!(
+        "TZID={}:{}",
+        timezone(),
+        localize(dt).format(LOCAL_DATE_TIME_FORMAT)
+    )
+}
+
+/// A UTC offset as a UTC-OFFSET value, e.g. `+0400`.
+fn format_utc_offset(offset: &FixedOffset) -> String {
+    let seconds = offset.local_minus_utc();
+    let sign = if seconds < 0 { '-' } else { '+' };
+    let seconds = seconds.abs();
+
+    let mut s = format!("{sign}{:02}{:02}", seconds / 3600, seconds / 60 % 60);
+    if seconds % 60 != 0 {
+        s = format!("{s}{:02}", seconds % 60);
+    }
+
+    s
+}
+
+/// The instant in `(after, before]` when the offset of the timezone changes,
+/// the offsets at the two instants must differ.
+fn find_transition(tz: Tz, after: DateTime<Utc>, before: DateTime<Utc>) -> DateTime<Utc> {
+    let offset = tz.offset_from_utc_datetime(&after.naive_utc());
+    let (mut after, mut before) = (after, before);
+
+    while before - after > Duration::seconds(1) {
+        let middle = after + (before - after) / 2;
+
+        if tz.offset_from_utc_datetime(&middle.naive_utc()) == offset {
+            after = middle;
+        } else {
+            before = middle;
+        }
+    }
+
+    before
+}
+
+/// Adds a STANDARD or DAYLIGHT observance which starts at the instant with the given offsets.
+fn push_observance(
+    lines: &mut Vec<String>,
+    starts_at: DateTime<Utc>,
+    offset_from: &FixedOffset,
+    offset: &<Tz as TimeZone>::Offset,
+) {
+    let component = if offset.dst_offset().is_zero() {
+        "STANDARD"
+    } else {
+        "DAYLIGHT"
+    };
+
+    // the start of an observance is the local time in the offset which was in effect before it
+    let local_start =
+        starts_at.naive_utc() + Duration::seconds(offset_from.local_minus_utc().into());
+
+    lines.push(format!("BEGIN:{component}"));
+    lines.push(format!(
+        "DTSTART:{}",
+        local_start.format(LOCAL_DATE_TIME_FORMAT)
+    ));
+    lines.push(format!("TZOFFSETFROM:{}", format_utc_offset(offset_from)));
+    lines.push(format!("TZOFFSETTO:{}", format_utc_offset(&offset.fix())));
+    lines.push(format!("TZNAME:{}", escape_text(offset.abbreviation())));
+    lines.push(format!("END:{component}"));
+}
+
+/// Adds a VTIMEZONE with every offset of the timezone from `from` to `until`,
+/// chrono-tz doesn't expose its transitions, so they are looked for hour by hour.
+fn push_timezone(
+    lines: &mut Vec<String>,
+    tz: Tz,
+    from: &DateTime<FixedOffset>,
+    until: &DateTime<FixedOffset>,
+) {
+    let from = from.with_timezone(&Utc);
+    let until = until.with_timezone(&Utc);
+
+    let mut offset = tz.offset_from_utc_datetime(&from.naive_utc());
+
+    lines.push("BEGIN:VTIMEZONE".to_owned());
+    lines.push(format!("TZID:{tz}"));
+    push_observance(lines, from, &offset.fix(), &offset);
+
+    let mut checked = from;
+    while checked < until {
+        let next = (checked + Duration::hours(1)).min(until);
+        let next_offset = tz.offset_from_utc_datetime(&next.naive_utc());
+
+        if next_offset != offset {
+            let transition = find_transition(tz, checked, next);
+            let transition_offset = tz.offset_from_utc_datetime(&transition.naive_utc());
+
+            push_observance(lines, transition, &offset.fix(), &transition_offset);
+            offset = transition_offset;
+        }
+
+        checked = next;
+    }
+
+    lines.push("END:VTIMEZONE".to_owned());
+}
+
+/// Finds the first date, not earlier than `from`, when the entry takes place.
+fn first_occurrence(
+    entry: &TimeTableEntry,
+    from: NaiveDate,
+    semesters: &SemesterCalendar,
+) -> Option<DateTime<FixedOffset>> {
+    let week = Week::containing(from);
+    let weekday: Weekday = entry.day_of_week.into();
+
+    (0..3)
+        .map(|offset| {
+            week.offset(offset).monday() + Duration::days(weekday.num_days_from_monday().into())
+        })
+        .filter(|date| *date >= from)
+        .find(|date| semesters.week_type(*date) == Some(entry.week))
+        .and_then(|date| local_datetime(date, entry.starts_at).ok())
+}
+
+fn end_of_day(date: NaiveDate) -> Result<DateTime<FixedOffset>> {
+    let time = NaiveTime::from_hms_opt(23, 59, 59).ok_or(anyhow!("invalid time"))?;
+    local_datetime(date, time)
+}
+
+/// The last day of the ISO year, odd and even weeks alternate only within a single year.
+fn end_of_iso_year(date: NaiveDate) -> Result<NaiveDate> {
+    let year = Week::containing(date).iso_week().year();
+
+    Week::from_iso(year, 53)
+        .or_else(|| Week::from_iso(year, 52))
+        .map(|week| week.sunday())
+        .ok_or(anyhow!("failed to find the end of the year {year}"))
+}
+
+fn push_event(
+    lines: &mut Vec<String>,
+    entry: &TimeTableEntry,
+    dt: &DateTime<FixedOffset>,
+    starts_at: DateTime<FixedOffset>,
+    until: &DateTime<FixedOffset>,
+) {
+    let ends_at = starts_at + (entry.ends_at - entry.starts_at);
+
+    lines.push("BEGIN:VEVENT".to_owned());
+    lines.push(format!("UID:{}@uni-bot", entry.lesson_id()));
+    lines.push(format!("DTSTAMP:{}", format_utc(dt)));
+    lines.push(format!("DTSTART;{}", format_local(&starts_at)));
+    lines.push(format!("DTEND;{}", format_local(&ends_at)));
+
+    if starts_at < *until {
+        lines.push(format!(
+            "RRULE:FREQ=WEEKLY;INTERVAL=2;UNTIL={}",
+            format_utc(until)
+        ));
+    }
+
+    lines.push(format!(
+        "SUMMARY:{}",
+        escape_text(&format!("{} ({})", entry.subject_name, entry.subject_type))
+    ));
+    lines.push(format!("LOCATION:{}", escape_text(&entry.auditorium)));
+
+    if let Some(professor) = entry.professor.as_ref() {
+        lines.push(format!("DESCRIPTION:{}", escape_text(professor)));
+    }
+
+    lines.push("END:VEVENT".to_owned());
+}
+
+/// Builds an RFC 5545 calendar with a recurring event for every entry,
+/// which lasts until the end of the current (or the next) semester of its major.
+pub fn make_calendar(
+    name: &str,
+    timetables: &[(SemesterCalendar, Vec<TimeTableEntry>)],
+    dt: &DateTime<FixedOffset>,
+) -> Result<String> {
+    let today = dt.date_naive();
+    let from_midnight = local_datetime(today, NaiveTime::MIN)?;
+
+    let mut events = vec![];
+    let mut last_until = end_of_day(today)?;
+
+    for (semesters, entries) in timetables {
+        let (from, until) = match semesters.current_or_next(today) {
+            Some(semester) => (semester.starts_on.max(today), semester.ends_on),
+            None => (today, end_of_iso_year(today)?),
+        };
+        let until = end_of_day(until)?;
+        last_until = last_until.max(until);
+
+        for entry in entries {
+            if let Some(starts_at) = first_occurrence(entry, from, semesters) {
+                push_event(&mut events, entry, dt, starts_at, &until);
+            }
+        }
+    }
+
+    let mut lines = vec![
+        "BEGIN:VCALENDAR".to_owned(),
+        "VERSION:2.0".to_owned(),
+        "PRODID:-//uni-bot//timetable//RU".to_owned(),
+        "CALSCALE:GREGORIAN".to_owned(),
+        format!("X-WR-CALNAME:{}", escape_text(name)),
+        format!("X-WR-TIMEZONE:{}", timezone()),
+    ];
+
+    // every TZID which the events refer to must be defined in the calendar
+    push_timezone(&mut lines, timezone(), &from_midnight, &last_until);
+
+    lines.extend(events);
+    lines.push("END:VCALENDAR".to_owned());
+
+    let calendar = lines
+        .iter()
+        .map(|line| fold_line(line))
+        .collect::<Vec<_>>()
+        .join("\r\n");
+
+    Ok(format!("{calendar}\r\n"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::storage::fixtures::date;
+
+    fn utc(year: i32, month: u32, day: u32) -> DateTime<FixedOffset> {
+        let date = date(year, month, day);
+        Utc.from_utc_datetime(&date.and_time(NaiveTime::MIN)).into()
+    }
+
+    fn timezone_lines(tz: Tz, from: DateTime<FixedOffset>, until: DateTime<FixedOffset>) -> String {
+        let mut lines = vec![];
+        push_timezone(&mut lines, tz, &from, &until);
+        lines.join("\n")
+    }
+
+    #[test]
+    fn utc_offsets() {
+        let offset = |seconds| format_utc_offset(&FixedOffset::east_opt(seconds).unwrap());
+
+        assert_eq!(offset(4 * 3600), "+0400");
+        assert_eq!(offset(0), "+0000");
+        assert_eq!(offset(-(3 * 3600 + 30 * 60)), "-0330");
+        assert_eq!(offset(5 * 3600 + 30 * 60 + 15), "+053015");
+    }
+
+    #[test]
+    fn timezone_without_dst_has_a_single_observance() {
+        let lines = timezone_lines(
+            chrono_tz::Europe::Samara,
+            utc(2024, 1, 1),
+            utc(2024, 12, 31),
+        );
+
+        assert_eq!(
+            lines,
+            [
+                "BEGIN:VTIMEZONE",
+                "TZID:Europe/Samara",
+                "BEGIN:STANDARD",
+                "DTSTART:20240101T040000",
+                "TZOFFSETFROM:+0400",
+                "TZOFFSETTO:+0400",
+                "TZNAME:+04",
+                "END:STANDARD",
+                "END:VTIMEZONE",
+            ]
+            .join("\n")
+        );
+    }
+
+    #[test]
+    fn timezone_with_dst_lists_every_transition() {
+        let lines = timezone_lines(
+            chrono_tz::Europe::Berlin,
+            utc(2024, 1, 1),
+            utc(2024, 12, 31),
+        );
+
+        assert_eq!(
+            lines,
+            [
+                "BEGIN:VTIMEZONE",
+                "TZID:Europe/Berlin",
+                "BEGIN:STANDARD",
+                "DTSTART:20240101T010000",
+                "TZOFFSETFROM:+0100",
+                "TZOFFSETTO:+0100",
+                "TZNAME:CET",
+                "END:STANDARD",
+                "BEGIN:DAYLIGHT",
+                "DTSTART:20240331T020000",
+                "TZOFFSETFROM:+0100",
+                "TZOFFSETTO:+0200",
+                "TZNAME:CEST",
+                "END:DAYLIGHT",
+                "BEGIN:STANDARD",
+                "DTSTART:20241027T030000",
+                "TZOFFSETFROM:+0200",
+                "TZOFFSETTO:+0100",
+                "TZNAME:CET",
+                "END:STANDARD",
+                "END:VTIMEZONE",
+            ]
+            .join("\n")
+        );
+    }
+}
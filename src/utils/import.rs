@@ -0,0 +1,542 @@
+use std::collections::HashMap;
+
+use anyhow::{anyhow, bail, Result};
+use chrono::NaiveTime;
+use serde::Deserialize;
+
+use super::{
+    durations::LessonDurations,
+    sql::types::{
+        DayOfWeek, NewTimeTableEntry, TimeTableEntry, TimeTableKey, WeekType,
+        MAX_LESSON_DURATION_MINUTES,
+    },
+};
+
+/// Largest document which is accepted for an import.
+pub const MAX_IMPORT_SIZE: u32 = 1024 * 1024;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ImportFormat {
+    Json,
+    Csv,
+}
+
+impl ImportFormat {
+    /// Guesses the format by the extension of the uploaded document.
+    pub fn from_file_name(name: &str) -> Option<Self> {
+        let (_, extension) = name.rsplit_once('.')?;
+
+        match extension.to_lowercase().as_str() {
+            "json" => Some(Self::Json),
+            "csv" => Some(Self::Csv),
+            _ => None,
+        }
+    }
+}
+
+/// A single lesson as it is written in the imported document.
+#[derive(Debug, Deserialize)]
+struct ImportRow {
+    week: WeekType,
+    day_of_week: DayOfWeek,
+    starts_at: String,
+    subject_name: String,
+    subject_type: String,
+    auditorium: String,
+    #[serde(default)]
+    professor: Option<String>,
+    #[serde(default)]
+    duration_minutes: Option<i16>,
+    /// Empty for lessons of the whole major.
+    #[serde(default)]
+    subgroup: Option<String>,
+}
+
+impl ImportRow {
+    fn into_entry(self, major_id: &str, durations: &LessonDurations) -> Result<NewTimeTableEntry> {
+        let starts_at = NaiveTime::parse_from_str(self.starts_at.trim(), "%H:%M")
+            .map_err(|_| anyhow!("неверное время {:?}, ожидалось ЧЧ:ММ", self.starts_at))?;
+
+        let subject_name = self.subject_name.trim();
+        let subject_type = self.subject_type.trim();
+        let auditorium = self.auditorium.trim();
+
+        if subject_name.is_empty() || subject_type.is_empty() || auditorium.is_empty() {
+            bail!("subject_name, subject_type и auditorium не могут быть пустыми");
+        }
+
+        let duration_minutes = match self.duration_minutes {
+            Some(minutes) => check_duration(minutes)?,
+            None => durations.minutes_for(major_id, subject_type),
+        };
+
+        Ok(NewTimeTableEntry {
+            key: TimeTableKey {
+                major_id: major_id.to_owned(),
+                subgroup: self
+                    .subgroup
+                    .map(|value| value.trim().to_owned())
+                    .filter(|value| !value.is_empty()),
+                week: self.week,
+                day_of_week: self.day_of_week,
+                starts_at,
+            },
+            subject_name: subject_name.to_owned(),
+            subject_type: subject_type.to_owned(),
+            auditorium: auditorium.to_owned(),
+            professor: self
+                .professor
+                .map(|value| value.trim().to_owned())
+                .filter(|value| !value.is_empty()),
+            duration_minutes,
+        })
+    }
+}
+
+/// Validates an explicitly given duration against the `CHECK` constraint of `timetable`.
+pub fn check_duration(minutes: i16) -> Result<i16> {
+    if !(1..=MAX_LESSON_DURATION_MINUTES).contains(&minutes) {
+        bail!(
+            "длительность должна быть от 1 до {MAX_LESSON_DURATION_MINUTES} минут, получено {minutes}"
+        );
+    }
+
+    Ok(minutes)
+}
+
+fn read_rows(format: ImportFormat, data: &[u8]) -> Result<Vec<ImportRow>> {
+    let rows = match format {
+        ImportFormat::Json => serde_json::from_slice::<Vec<ImportRow>>(data)?,
+        ImportFormat::Csv => {
+            let mut reader = csv::ReaderBuilder::new()
+                .trim(csv::Trim::All)
+                .from_reader(data);
+
+            reader
+                .deserialize::<ImportRow>()
+                .enumerate()
+                .map(|(i, row)| row.map_err(|err| anyhow!("строка {}: {err}", i + 1)))
+                .collect::<Result<Vec<_>>>()?
+        }
+    };
+
+    Ok(rows)
+}
+
+/// Parses a document into entries of the given major,
+/// rows without a duration get the default one of their subject type.
+///
+/// Rows are validated against the unique index on the slot and the subgroup of a lesson,
+/// so the whole document is rejected if two rows share the same slot.
+pub fn parse_document(
+    format: ImportFormat,
+    data: &[u8],
+    major_id: &str,
+    durations: &LessonDurations,
+) -> Result<Vec<NewTimeTableEntry>> {
+    let rows = read_rows(format, data)?;
+
+    let mut seen: HashMap<TimeTableKey, usize> = HashMap::new();
+    let mut entries = Vec::with_capacity(rows.len());
+
+    for (i, row) in rows.into_iter().enumerate() {
+        let row_number = i + 1;
+        let entry = row
+            .into_entry(major_id, durations)
+            .map_err(|err| anyhow!("строка {row_number}: {err}"))?;
+
+        if let Some(previous) = seen.insert(entry.key.clone(), row_number) {
+            bail!(
+                "строка {row_number} совпадает со строкой {previous} по неделе, дню, времени и подгруппе: {} {} {}",
+                entry.key.week,
+                entry.key.day_of_week,
+                entry.key.starts_at.format("%H:%M")
+            );
+        }
+
+        entries.push(entry);
+    }
+
+    if entries.is_empty() {
+        bail!("в документе нет строк");
+    }
+
+    Ok(entries)
+}
+
+/// Difference between the stored timetable of a major and the imported one.
+#[derive(Debug, Default)]
+pub struct TimetableDiff {
+    pub added: Vec<NewTimeTableEntry>,
+    pub changed: Vec<(TimeTableEntry, NewTimeTableEntry)>,
+    pub removed: Vec<TimeTableEntry>,
+    pub unchanged: usize,
+}
+
+impl TimetableDiff {
+    pub fn new(existing: Vec<TimeTableEntry>, imported: Vec<NewTimeTableEntry>) -> Self {
+        let mut existing: HashMap<TimeTableKey, TimeTableEntry> = existing
+            .into_iter()
+            .map(|entry| (entry.key(), entry))
+            .collect();
+
+        let mut diff = Self::default();
+
+        for entry in imported {
+            match existing.remove(&entry.key) {
+                None => diff.added.push(entry),
+                Some(old) if is_same_lesson(&old, &entry) => diff.unchanged += 1,
+                Some(old) => diff.changed.push((old, entry)),
+            }
+        }
+
+        diff.removed = existing.into_values().collect();
+        diff.removed.sort_by_key(|entry| entry.key());
+
+        diff
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.changed.is_empty() && self.removed.is_empty()
+    }
+}
+
+fn is_same_lesson(old: &TimeTableEntry, new: &NewTimeTableEntry) -> bool {
+    old.subject_name == new.subject_name
+        && old.subject_type == new.subject_type
+        && old.auditorium == new.auditorium
+        && old.professor == new.professor
+        && old.duration_minutes == new.duration_minutes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::{sql::types::LessonDurationEntry, storage::fixtures::entry};
+
+    const CSV_HEADER: &str =
+        "week,day_of_week,starts_at,subject_name,subject_type,auditorium,professor,duration_minutes,subgroup";
+
+    fn csv(rows: &[&str]) -> Vec<u8> {
+        let mut data = CSV_HEADER.to_owned();
+        for row in rows {
+            data = format!("{data}\n{row}");
+        }
+
+        data.into_bytes()
+    }
+
+    fn durations() -> LessonDurations {
+        LessonDurations::new(vec![LessonDurationEntry {
+            id: 1,
+            major_id: None,
+            subject_type: Some("Лабораторная".to_owned()),
+            duration_minutes: 180,
+        }])
+    }
+
+    /// Subject, duration, subgroup and professor of a parsed row.
+    type Parsed = (
+        &'static str,
+        i16,
+        Option<&'static str>,
+        Option<&'static str>,
+    );
+
+    fn parse(format: ImportFormat, data: &[u8]) -> Result<Vec<NewTimeTableEntry>> {
+        parse_document(format, data, "ivt", &durations())
+    }
+
+    #[test]
+    fn valid_documents_are_parsed() {
+        let cases: &[(&str, ImportFormat, Vec<u8>, &[Parsed])] = &[
+            (
+                "csv with defaults",
+                ImportFormat::Csv,
+                csv(&[
+                    "odd,monday,09:00,Физика,Лекция,301,Иванов И.И.,,",
+                    "odd,monday,11:00,Физика,Лабораторная,Лаб. 2,,,1",
+                ]),
+                &[
+                    ("Физика", 90, None, Some("Иванов И.И.")),
+                    ("Физика", 180, Some("1"), None),
+                ],
+            ),
+            (
+                "csv with an explicit duration",
+                ImportFormat::Csv,
+                csv(&["even,friday, 13:00 ,Физкультура,Практика,Спортзал,,45,"]),
+                &[("Физкультура", 45, None, None)],
+            ),
+            (
+                "same slot of different subgroups",
+                ImportFormat::Csv,
+                csv(&[
+                    "odd,monday,11:00,Химия,Лабораторная,201,,,1",
+                    "odd,monday,11:00,Химия,Лабораторная,202,,,2",
+                ]),
+                &[
+                    ("Химия", 180, Some("1"), None),
+                    ("Химия", 180, Some("2"), None),
+                ],
+            ),
+            (
+                "json",
+                ImportFormat::Json,
+                r#"[
+                    {"week": "odd", "day_of_week": "monday", "starts_at": "09:00",
+                     "subject_name": "Физика", "subject_type": "Лекция", "auditorium": "301"},
+                    {"week": "even", "day_of_week": "monday", "starts_at": "09:00",
+                     "subject_name": "Химия", "subject_type": "Лабораторная", "auditorium": "201",
+                     "professor": " ", "subgroup": ""}
+                ]"#
+                .as_bytes()
+                .to_vec(),
+                &[("Физика", 90, None, None), ("Химия", 180, None, None)],
+            ),
+        ];
+
+        for (name, format, data, expected) in cases {
+            let entries = parse(*format, data).unwrap_or_else(|err| panic!("{name}: {err}"));
+            let actual = entries
+                .iter()
+                .map(|entry| {
+                    (
+                        entry.subject_name.as_str(),
+                        entry.duration_minutes,
+                        entry.key.subgroup.as_deref(),
+                        entry.professor.as_deref(),
+                    )
+                })
+                .collect::<Vec<_>>();
+
+            assert_eq!(&actual, expected, "{name}");
+            assert!(entries.iter().all(|entry| entry.key.major_id == "ivt"));
+        }
+    }
+
+    #[test]
+    fn invalid_documents_are_rejected() {
+        let cases: &[(&str, ImportFormat, Vec<u8>, &str)] = &[
+            (
+                "duplicate slot",
+                ImportFormat::Csv,
+                csv(&[
+                    "odd,monday,09:00,Физика,Лекция,301,,,",
+                    "even,monday,09:00,Физика,Лекция,301,,,",
+                    "odd,monday,09:00,Химия,Лекция,302,,,",
+                ]),
+                "строка 3 совпадает со строкой 1 по неделе, дню, времени и подгруппе",
+            ),
+            (
+                "duplicate slot of a subgroup",
+                ImportFormat::Csv,
+                csv(&[
+                    "odd,monday,11:00,Химия,Лабораторная,201,,,1",
+                    "odd,monday,11:00,Химия,Лабораторная,202,,, 1 ",
+                ]),
+                "строка 2 совпадает",
+            ),
+            (
+                "неверное время",
+                ImportFormat::Csv,
+                csv(&["odd,monday,9am,Физика,Лекция,301,,,"]),
+                "строка 1: неверное время",
+            ),
+            (
+                "empty subject",
+                ImportFormat::Csv,
+                csv(&[
+                    "odd,monday,09:00,Физика,Лекция,301,,,",
+                    "odd,monday,11:00, ,Лекция,301,,,",
+                ]),
+                "строка 2: subject_name, subject_type и auditorium не могут быть пустыми",
+            ),
+            (
+                "unknown week",
+                ImportFormat::Csv,
+                csv(&["weekly,monday,09:00,Физика,Лекция,301,,,"]),
+                "строка 1:",
+            ),
+            (
+                "missing column",
+                ImportFormat::Csv,
+                csv(&["odd,monday,09:00,Физика"]),
+                "строка 1:",
+            ),
+            (
+                "duration out of range",
+                ImportFormat::Csv,
+                csv(&["odd,monday,09:00,Физика,Лекция,301,,0,"]),
+                "строка 1: длительность должна быть от 1 до 720 минут, получено 0",
+            ),
+            (
+                "json row without a field",
+                ImportFormat::Json,
+                br#"[{"week": "odd"}]"#.to_vec(),
+                "missing field `day_of_week`",
+            ),
+            (
+                "malformed json",
+                ImportFormat::Json,
+                br#"[{"week": "odd""#.to_vec(),
+                "EOF",
+            ),
+            (
+                "no rows",
+                ImportFormat::Json,
+                b"[]".to_vec(),
+                "в документе нет строк",
+            ),
+            (
+                "header only",
+                ImportFormat::Csv,
+                csv(&[]),
+                "в документе нет строк",
+            ),
+        ];
+
+        for (name, format, data, expected) in cases {
+            let err = match parse(*format, data) {
+                Ok(entries) => panic!("{name}: accepted {} rows", entries.len()),
+                Err(err) => err.to_string(),
+            };
+
+            assert!(err.contains(expected), "{name}: {err}");
+        }
+    }
+
+    fn stored(id: i64, entry: NewTimeTableEntry) -> TimeTableEntry {
+        TimeTableEntry {
+            id,
+            ..entry.preview()
+        }
+    }
+
+    #[test]
+    fn diff_against_the_stored_timetable() {
+        use DayOfWeek::{Monday, Tuesday, Wednesday};
+
+        let physics = entry(Monday, 9, "Физика");
+        let chemistry = entry(Tuesday, 11, "Химия");
+        let history = entry(Wednesday, 13, "История");
+
+        struct Case {
+            name: &'static str,
+            existing: Vec<TimeTableEntry>,
+            imported: Vec<NewTimeTableEntry>,
+            added: Vec<&'static str>,
+            changed: Vec<(i64, &'static str)>,
+            removed: Vec<i64>,
+            unchanged: usize,
+        }
+
+        let cases = vec![
+            Case {
+                name: "empty timetable",
+                existing: vec![],
+                imported: vec![physics.clone(), chemistry.clone()],
+                added: vec!["Физика", "Химия"],
+                changed: vec![],
+                removed: vec![],
+                unchanged: 0,
+            },
+            Case {
+                name: "same timetable",
+                existing: vec![stored(1, physics.clone()), stored(2, chemistry.clone())],
+                imported: vec![chemistry.clone(), physics.clone()],
+                added: vec![],
+                changed: vec![],
+                removed: vec![],
+                unchanged: 2,
+            },
+            Case {
+                name: "added, changed and removed",
+                existing: vec![
+                    stored(1, physics.clone()),
+                    stored(2, chemistry.clone()),
+                    stored(3, history.clone()),
+                ],
+                imported: vec![
+                    physics.clone(),
+                    NewTimeTableEntry {
+                        auditorium: "302".to_owned(),
+                        ..chemistry.clone()
+                    },
+                    entry(Wednesday, 15, "История"),
+                ],
+                added: vec!["История"],
+                changed: vec![(2, "Химия")],
+                removed: vec![3],
+                unchanged: 1,
+            },
+            Case {
+                name: "another subject in the slot",
+                existing: vec![stored(1, physics.clone())],
+                imported: vec![entry(Monday, 9, "Матанализ")],
+                added: vec![],
+                changed: vec![(1, "Матанализ")],
+                removed: vec![],
+                unchanged: 0,
+            },
+            Case {
+                name: "only the duration differs",
+                existing: vec![stored(1, physics.clone())],
+                imported: vec![NewTimeTableEntry {
+                    duration_minutes: 45,
+                    ..physics.clone()
+                }],
+                added: vec![],
+                changed: vec![(1, "Физика")],
+                removed: vec![],
+                unchanged: 0,
+            },
+            Case {
+                name: "another subgroup is another slot",
+                existing: vec![stored(1, physics.clone())],
+                imported: vec![NewTimeTableEntry {
+                    key: TimeTableKey {
+                        subgroup: Some("1".to_owned()),
+                        ..physics.key.clone()
+                    },
+                    ..physics.clone()
+                }],
+                added: vec!["Физика"],
+                changed: vec![],
+                removed: vec![1],
+                unchanged: 0,
+            },
+        ];
+
+        for case in cases {
+            let diff = TimetableDiff::new(case.existing, case.imported);
+            let name = case.name;
+
+            let added = diff
+                .added
+                .iter()
+                .map(|entry| entry.subject_name.as_str())
+                .collect::<Vec<_>>();
+            let changed = diff
+                .changed
+                .iter()
+                .map(|(old, new)| (old.id, new.subject_name.as_str()))
+                .collect::<Vec<_>>();
+            let removed = diff
+                .removed
+                .iter()
+                .map(|entry| entry.id)
+                .collect::<Vec<_>>();
+
+            assert_eq!(added, case.added, "{name}");
+            assert_eq!(changed, case.changed, "{name}");
+            assert_eq!(removed, case.removed, "{name}");
+            assert_eq!(diff.unchanged, case.unchanged, "{name}");
+            assert_eq!(
+                diff.is_empty(),
+                case.added.is_empty() && case.changed.is_empty() && case.removed.is_empty(),
+                "{name}"
+            );
+        }
+    }
+}
@@ -1,3 +1,12 @@
 pub mod database;
+pub mod durations;
+pub mod fuzzy;
+pub mod i18n;
+pub mod ical;
+pub mod overrides;
+pub mod import;
+pub mod pending;
+pub mod semester;
 pub mod time;
 pub mod sql;
+pub mod storage;
@@ -0,0 +1,285 @@
+use chrono::{Datelike, Duration, NaiveDate};
+
+use super::sql::types::{
+    OverrideEntry, OverrideKind, TimeTableEntry, WeekType, DEFAULT_LESSON_DURATION_MINUTES,
+};
+
+/// The original lesson as it looks after the move.
+pub fn moved_entry(original: &TimeTableEntry, entry: &OverrideEntry) -> TimeTableEntry {
+    let starts_at = entry.starts_at.unwrap_or(original.starts_at);
+
+    TimeTableEntry {
+        starts_at,
+        ends_at: starts_at + (original.ends_at - original.starts_at),
+        auditorium: entry
+            .auditorium
+            .clone()
+            .unwrap_or_else(|| original.auditorium.clone()),
+        moved_from: Some(entry.date),
+        ..original.clone()
+    }
+}
+
+/// A one-off lesson as a timetable entry, `None` if the override is not a complete lesson.
+pub fn extra_entry(entry: &OverrideEntry) -> Option<TimeTableEntry> {
+    let starts_at = entry.starts_at?;
+    let duration_minutes = entry
+        .duration_minutes
+        .unwrap_or(DEFAULT_LESSON_DURATION_MINUTES);
+
+    Some(TimeTableEntry {
+        id: entry.id,
+        major_id: entry.major_id.clone(),
+        // one-off lessons don't depend on the week, it is filled just to complete the entry
+        week: WeekType::from_iso_week(entry.date),
+        day_of_week: entry.date.weekday().into(),
+        starts_at,
+        duration_minutes,
+        ends_at: starts_at + Duration::minutes(duration_minutes.into()),
+        subject_name: entry.subject_name.clone()?,
+        subject_type: entry.subject_type.clone()?,
+        auditorium: entry.auditorium.clone()?,
+        professor: entry.professor.clone(),
+        subgroup: None,
+        moved_from: None,
+        elective: false,
+        extra: true,
+    })
+}
+
+/// Merges overrides of the date on top of the recurring entries of that date.
+///
+/// `originals` must contain the timetable rows which are moved to the date.
+pub fn apply_overrides(
+    date: NaiveDate,
+    recurring: Vec<TimeTableEntry>,
+    overrides: &[OverrideEntry],
+    originals: &[TimeTableEntry],
+) -> Vec<TimeTableEntry> {
+    let is_holiday = overrides
+        .iter()
+        .any(|entry| entry.kind == OverrideKind::Holiday && entry.date == date);
+
+    let mut entries: Vec<TimeTableEntry> = if is_holiday {
+        vec![]
+    } else {
+        recurring
+            .into_iter()
+            .filter(|recurring| {
+                !overrides.iter().any(|entry| {
+                    matches!(entry.kind, OverrideKind::Cancel | OverrideKind::Move)
+                        && entry.date == date
+                        && entry.timetable_id == Some(recurring.id)
+                })
+            })
+            .collect()
+    };
+
+    for entry in overrides {
+        match entry.kind {
+            OverrideKind::Move if entry.new_date == Some(date) => {
+                let original = originals
+                    .iter()
+                    .find(|original| Some(original.id) == entry.timetable_id);
+
+                if let Some(original) = original {
+                    entries.push(moved_entry(original, entry));
+                }
+            }
+
+            OverrideKind::Extra if entry.date == date => {
+                entries.extend(extra_entry(entry));
+            }
+
+            _ => {}
+        }
+    }
+
+    entries.sort_by_key(|entry| entry.starts_at);
+    entries
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::storage::fixtures::{date, lesson, time};
+
+    fn override_entry(id: i64, kind: OverrideKind, date: NaiveDate) -> OverrideEntry {
+        OverrideEntry {
+            id,
+            kind,
+            major_id: Some("ivt".to_owned()),
+            timetable_id: None,
+            date,
+            new_date: None,
+            starts_at: None,
+            subject_name: None,
+            subject_type: None,
+            auditorium: None,
+            professor: None,
+            duration_minutes: None,
+        }
+    }
+
+    fn cancel(id: i64, timetable_id: i64, date: NaiveDate) -> OverrideEntry {
+        OverrideEntry {
+            timetable_id: Some(timetable_id),
+            ..override_entry(id, OverrideKind::Cancel, date)
+        }
+    }
+
+    fn move_to(
+        id: i64,
+        timetable_id: i64,
+        date: NaiveDate,
+        new_date: NaiveDate,
+        hour: u32,
+    ) -> OverrideEntry {
+        OverrideEntry {
+            timetable_id: Some(timetable_id),
+            new_date: Some(new_date),
+            starts_at: Some(time(hour)),
+            ..override_entry(id, OverrideKind::Move, date)
+        }
+    }
+
+    fn ids(entries: &[TimeTableEntry]) -> Vec<i64> {
+        entries.iter().map(|entry| entry.id).collect()
+    }
+
+    #[test]
+    fn cancel_removes_the_occurrence() {
+        let overrides = [cancel(1, 1000, date(2023, 3, 16))];
+        let entries = apply_overrides(
+            date(2023, 3, 16),
+            vec![
+                lesson(1000, "ivt", 9, "Физика"),
+                lesson(1001, "ivt", 11, "Физика"),
+            ],
+            &overrides,
+            &[],
+        );
+
+        assert_eq!(ids(&entries), [1001]);
+    }
+
+    #[test]
+    fn cancel_of_another_date_is_ignored() {
+        let overrides = [cancel(1, 1000, date(2023, 3, 23))];
+        let entries = apply_overrides(
+            date(2023, 3, 16),
+            vec![lesson(1000, "ivt", 9, "Физика")],
+            &overrides,
+            &[],
+        );
+
+        assert_eq!(ids(&entries), [1000]);
+    }
+
+    #[test]
+    fn move_to_a_later_date() {
+        let overrides = [move_to(1, 1000, date(2023, 3, 16), date(2023, 3, 18), 13)];
+        let original = lesson(1000, "ivt", 9, "Физика");
+
+        let old_day = apply_overrides(date(2023, 3, 16), vec![original.clone()], &overrides, &[]);
+        assert!(old_day.is_empty());
+
+        let new_day = apply_overrides(date(2023, 3, 18), vec![], &overrides, &[original]);
+        assert_eq!(ids(&new_day), [1000]);
+        assert_eq!(new_day[0].starts_at, time(13));
+        assert_eq!(new_day[0].ends_at, time(13) + Duration::minutes(90));
+        assert_eq!(new_day[0].moved_from, Some(date(2023, 3, 16)));
+    }
+
+    #[test]
+    fn move_to_an_earlier_date() {
+        let overrides = [move_to(1, 1000, date(2023, 3, 16), date(2023, 3, 14), 15)];
+        let original = lesson(1000, "ivt", 9, "Физика");
+
+        let new_day = apply_overrides(
+            date(2023, 3, 14),
+            vec![lesson(1001, "ivt", 9, "Физика")],
+            &overrides,
+            &[original],
+        );
+        assert_eq!(ids(&new_day), [1001, 1000]);
+        assert_eq!(new_day[1].starts_at, time(15));
+
+        let old_day = apply_overrides(
+            date(2023, 3, 16),
+            vec![lesson(1000, "ivt", 9, "Физика")],
+            &overrides,
+            &[],
+        );
+        assert!(old_day.is_empty());
+    }
+
+    #[test]
+    fn extra_lesson_is_added_in_order() {
+        let overrides = [OverrideEntry {
+            starts_at: Some(time(10)),
+            duration_minutes: Some(45),
+            subject_name: Some("Консультация".to_owned()),
+            subject_type: Some("Практика".to_owned()),
+            auditorium: Some("105".to_owned()),
+            ..override_entry(7, OverrideKind::Extra, date(2023, 3, 16))
+        }];
+
+        let entries = apply_overrides(
+            date(2023, 3, 16),
+            vec![
+                lesson(1000, "ivt", 9, "Физика"),
+                lesson(1001, "ivt", 11, "Физика"),
+            ],
+            &overrides,
+            &[],
+        );
+
+        assert_eq!(ids(&entries), [1000, 7, 1001]);
+        assert!(entries[1].extra);
+        assert_eq!(entries[1].lesson_id().to_string(), "extra-7");
+        assert_eq!(entries[1].ends_at, time(10) + Duration::minutes(45));
+    }
+
+    #[test]
+    fn holiday_removes_recurring_lessons_but_keeps_extra_ones() {
+        let overrides = [
+            override_entry(1, OverrideKind::Holiday, date(2023, 3, 16)),
+            OverrideEntry {
+                starts_at: Some(time(10)),
+                subject_name: Some("Консультация".to_owned()),
+                subject_type: Some("Практика".to_owned()),
+                auditorium: Some("105".to_owned()),
+                ..override_entry(2, OverrideKind::Extra, date(2023, 3, 16))
+            },
+        ];
+
+        let entries = apply_overrides(
+            date(2023, 3, 16),
+            vec![
+                lesson(1000, "ivt", 9, "Физика"),
+                lesson(1001, "ivt", 11, "Физика"),
+            ],
+            &overrides,
+            &[],
+        );
+
+        assert_eq!(ids(&entries), [2]);
+    }
+
+    #[test]
+    fn cancel_and_move_of_the_same_occurrence() {
+        let overrides = [
+            cancel(1, 1000, date(2023, 3, 16)),
+            move_to(2, 1000, date(2023, 3, 16), date(2023, 3, 17), 9),
+        ];
+        let original = lesson(1000, "ivt", 9, "Физика");
+
+        let old_day = apply_overrides(date(2023, 3, 16), vec![original.clone()], &overrides, &[]);
+        assert!(old_day.is_empty());
+
+        // the occurrence is removed once, the moved copy is not cancelled by the other override
+        let new_day = apply_overrides(date(2023, 3, 17), vec![], &overrides, &[original]);
+        assert_eq!(ids(&new_day), [1000]);
+    }
+}
@@ -0,0 +1,53 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+};
+
+use rand::Rng;
+
+/// Keeps actions which are waiting for a confirmation button to be pressed.
+#[derive(Debug)]
+pub struct Pending<T> {
+    items: Arc<Mutex<HashMap<u64, T>>>,
+}
+
+impl<T> Clone for Pending<T> {
+    fn clone(&self) -> Self {
+        Self {
+            items: Arc::clone(&self.items),
+        }
+    }
+}
+
+impl<T> Default for Pending<T> {
+    fn default() -> Self {
+        Self {
+            items: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+}
+
+impl<T> Pending<T> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Stores the value and returns an id which fits into callback data.
+    pub fn insert(&self, value: T) -> u64 {
+        let mut items = self.items.lock().unwrap();
+
+        let mut rng = rand::rngs::OsRng;
+        let mut id: u64 = rng.gen();
+        while items.contains_key(&id) {
+            id = rng.gen();
+        }
+
+        items.insert(id, value);
+        id
+    }
+
+    /// Removes the value, so that the same action can't be applied twice.
+    pub fn take(&self, id: u64) -> Option<T> {
+        self.items.lock().unwrap().remove(&id)
+    }
+}
@@ -0,0 +1,168 @@
+use anyhow::Result;
+use chrono::NaiveDate;
+
+use super::{
+    sql::types::{SemesterEntry, WeekType},
+    storage::Storage,
+};
+
+/// Semesters which apply to a single major.
+#[derive(Debug, Clone)]
+pub struct SemesterCalendar {
+    semesters: Vec<SemesterEntry>,
+}
+
+impl SemesterCalendar {
+    /// Keeps the semesters of the major, global ones are used only if it has none.
+    pub fn new(semesters: Vec<SemesterEntry>) -> Self {
+        let has_own = semesters.iter().any(|semester| semester.major_id.is_some());
+        let semesters = semesters
+            .into_iter()
+            .filter(|semester| semester.major_id.is_some() == has_own)
+            .collect();
+
+        Self { semesters }
+    }
+
+    pub async fn load(storage: &dyn Storage, major_id: &str) -> Result<Self> {
+        let semesters = storage.get_semesters_for_major(major_id).await?;
+        Ok(Self::new(semesters))
+    }
+
+    /// Week type of the date, `None` if there are no classes since the date is outside of semesters.
+    ///
+    /// Falls back to the parity of the ISO week if no semesters are configured at all.
+    pub fn week_type(&self, date: NaiveDate) -> Option<WeekType> {
+        if self.semesters.is_empty() {
+            return Some(WeekType::from_iso_week(date));
+        }
+
+        self.semesters
+            .iter()
+            .find_map(|semester| semester.week_type(date))
+    }
+
+    /// The semester which is going on at the date or the closest one after it.
+    pub fn current_or_next(&self, date: NaiveDate) -> Option<&SemesterEntry> {
+        self.semesters
+            .iter()
+            .filter(|semester| semester.ends_on >= date)
+            .min_by_key(|semester| semester.starts_on)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::storage::{
+        fixtures::date,
+        memory::{MemoryStorage, State},
+    };
+
+    fn semester(
+        major_id: Option<&str>,
+        starts_on: NaiveDate,
+        ends_on: NaiveDate,
+        first_week: WeekType,
+    ) -> SemesterEntry {
+        SemesterEntry {
+            id: 0,
+            major_id: major_id.map(str::to_owned),
+            starts_on,
+            ends_on,
+            first_week,
+        }
+    }
+
+    #[test]
+    fn first_week_is_the_one_the_semester_starts_in() {
+        // starts on Wednesday
+        let calendar = SemesterCalendar::new(vec![semester(
+            None,
+            date(2023, 9, 6),
+            date(2023, 12, 30),
+            WeekType::Odd,
+        )]);
+
+        assert_eq!(calendar.week_type(date(2023, 9, 6)), Some(WeekType::Odd));
+        assert_eq!(calendar.week_type(date(2023, 9, 10)), Some(WeekType::Odd));
+        assert_eq!(calendar.week_type(date(2023, 9, 11)), Some(WeekType::Even));
+        assert_eq!(calendar.week_type(date(2023, 9, 18)), Some(WeekType::Odd));
+        // the days of the first week before the start
+        assert_eq!(calendar.week_type(date(2023, 9, 4)), None);
+    }
+
+    #[test]
+    fn weeks_keep_alternating_across_the_new_year() {
+        let calendar = SemesterCalendar::new(vec![semester(
+            None,
+            date(2020, 12, 14),
+            date(2021, 5, 31),
+            WeekType::Even,
+        )]);
+
+        // Dec 28 – Jan 3 is the 53rd ISO week and Jan 4 starts the 1st one,
+        // both are odd by the parity but the semester counts them in a row
+        assert_eq!(calendar.week_type(date(2020, 12, 21)), Some(WeekType::Odd));
+        assert_eq!(calendar.week_type(date(2020, 12, 31)), Some(WeekType::Even));
+        assert_eq!(calendar.week_type(date(2021, 1, 3)), Some(WeekType::Even));
+        assert_eq!(calendar.week_type(date(2021, 1, 4)), Some(WeekType::Odd));
+        assert_eq!(calendar.week_type(date(2021, 1, 11)), Some(WeekType::Even));
+    }
+
+    #[test]
+    fn dates_outside_of_semesters_have_no_classes() {
+        let calendar = SemesterCalendar::new(vec![
+            semester(None, date(2023, 9, 1), date(2023, 12, 30), WeekType::Odd),
+            semester(None, date(2024, 2, 5), date(2024, 6, 30), WeekType::Odd),
+        ]);
+
+        assert_eq!(calendar.week_type(date(2023, 8, 31)), None);
+        assert_eq!(calendar.week_type(date(2024, 1, 15)), None);
+        assert_eq!(calendar.week_type(date(2024, 7, 1)), None);
+        assert_eq!(calendar.week_type(date(2023, 12, 30)), Some(WeekType::Even));
+        assert_eq!(calendar.week_type(date(2024, 2, 5)), Some(WeekType::Odd));
+
+        assert_eq!(
+            calendar
+                .current_or_next(date(2024, 1, 15))
+                .unwrap()
+                .starts_on,
+            date(2024, 2, 5)
+        );
+        assert!(calendar.current_or_next(date(2024, 7, 1)).is_none());
+    }
+
+    #[test]
+    fn iso_week_parity_is_used_without_semesters() {
+        let calendar = SemesterCalendar::new(vec![]);
+
+        assert_eq!(calendar.week_type(date(2023, 3, 16)), Some(WeekType::Odd));
+        assert_eq!(calendar.week_type(date(2023, 3, 20)), Some(WeekType::Even));
+    }
+
+    #[tokio::test]
+    async fn semesters_of_the_major_replace_the_global_ones() {
+        let storage = MemoryStorage::new(State {
+            semesters: vec![
+                semester(None, date(2023, 9, 1), date(2023, 12, 30), WeekType::Odd),
+                semester(
+                    Some("pm"),
+                    date(2023, 9, 1),
+                    date(2023, 12, 16),
+                    WeekType::Even,
+                ),
+            ],
+            ..State::default()
+        });
+
+        let global = SemesterCalendar::load(&storage, "ivt").await.unwrap();
+        let own = SemesterCalendar::load(&storage, "pm").await.unwrap();
+
+        assert_eq!(global.week_type(date(2023, 9, 4)), Some(WeekType::Even));
+        assert_eq!(own.week_type(date(2023, 9, 4)), Some(WeekType::Odd));
+        // the global semester does not fill in after the own one has ended
+        assert_eq!(global.week_type(date(2023, 12, 20)), Some(WeekType::Odd));
+        assert_eq!(own.week_type(date(2023, 12, 20)), None);
+    }
+}
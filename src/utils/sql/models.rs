@@ -1,15 +1,656 @@
-use anyhow::{anyhow, Result};
+use anyhow::Result;
+use chrono::{NaiveDate, NaiveTime};
 use sqlx::PgExecutor;
 
-use super::types::UserEntry;
+use crate::utils::i18n::Language;
+
+use super::types::{
+    DayOfWeek, DigestSubscription, DueDigest, ElectiveEntry, LessonDurationEntry, LessonId,
+    MajorEntry, NewTimeTableEntry, OverrideEntry, ProfessorEntry, ReminderSetting, SemesterEntry,
+    SubgroupEntry, TimeTableEntry, TimeTableKey, UserEntry, WeekType,
+};
 
 pub async fn get_user_by_id_opt(
     executor: impl PgExecutor<'_>,
     id: i64,
 ) -> Result<Option<UserEntry>> {
-    let sql = r#"SELECT * FROM users WHERE id = $1;"#;
+    // users who only picked a language have no major yet
+    let sql = r#"SELECT * FROM users WHERE id = $1 AND major_id IS NOT NULL;"#;
+
+    let entry = sqlx::query_as::<_, UserEntry>(sql)
+        .bind(id)
+        .fetch_optional(executor)
+        .await?;
+
+    Ok(entry)
+}
+
+/// Creates the user if needed, the subgroup belongs to the previous major so it is reset.
+pub async fn set_user_major(
+    executor: impl PgExecutor<'_>,
+    id: i64,
+    major_id: &str,
+) -> Result<UserEntry> {
+    let sql = r#"INSERT INTO users (id, major_id) VALUES ($1, $2)
+        ON CONFLICT (id) DO UPDATE SET major_id = $2, subgroup = NULL
+        RETURNING *;"#;
 
     let entry = sqlx::query_as::<_, UserEntry>(sql)
+        .bind(id)
+        .bind(major_id)
+        .fetch_one(executor)
+        .await?;
+
+    Ok(entry)
+}
+
+/// Language of the user, the default one if the user is unknown.
+pub async fn get_user_language(executor: impl PgExecutor<'_>, id: i64) -> Result<Language> {
+    let sql = r#"SELECT language FROM users WHERE id = $1;"#;
+
+    let language = sqlx::query_scalar::<_, Language>(sql)
+        .bind(id)
+        .fetch_optional(executor)
+        .await?;
+
+    Ok(language.unwrap_or_default())
+}
+
+pub async fn set_user_language(
+    executor: impl PgExecutor<'_>,
+    id: i64,
+    language: Language,
+) -> Result<()> {
+    let sql = r#"INSERT INTO users (id, language) VALUES ($1, $2)
+        ON CONFLICT (id) DO UPDATE SET language = $2;"#;
+
+    sqlx::query(sql)
+        .bind(id)
+        .bind(language)
+        .execute(executor)
+        .await?;
+
+    Ok(())
+}
+
+pub async fn get_timetable_entry_by_key(
+    executor: impl PgExecutor<'_>,
+    key: &TimeTableKey,
+) -> Result<Option<TimeTableEntry>> {
+    let sql = r#"SELECT * FROM timetable
+        WHERE
+            major_id = $1
+            AND week = $2
+            AND day_of_week = $3
+            AND starts_at = $4
+            AND subgroup IS NOT DISTINCT FROM $5;"#;
+
+    let entry = sqlx::query_as::<_, TimeTableEntry>(sql)
+        .bind(&key.major_id)
+        .bind(key.week)
+        .bind(key.day_of_week)
+        .bind(key.starts_at)
+        .bind(&key.subgroup)
+        .fetch_optional(executor)
+        .await?;
+
+    Ok(entry)
+}
+
+pub async fn insert_timetable_entry(
+    executor: impl PgExecutor<'_>,
+    entry: &NewTimeTableEntry,
+) -> Result<TimeTableEntry> {
+    let sql = r#"INSERT INTO timetable
+        (major_id, week, day_of_week, starts_at, subject_name, subject_type, auditorium, professor,
+            duration_minutes, subgroup)
+        VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10)
+        RETURNING *;"#;
+
+    let entry = sqlx::query_as::<_, TimeTableEntry>(sql)
+        .bind(&entry.key.major_id)
+        .bind(entry.key.week)
+        .bind(entry.key.day_of_week)
+        .bind(entry.key.starts_at)
+        .bind(&entry.subject_name)
+        .bind(&entry.subject_type)
+        .bind(&entry.auditorium)
+        .bind(&entry.professor)
+        .bind(entry.duration_minutes)
+        .bind(&entry.key.subgroup)
+        .fetch_one(executor)
+        .await?;
+
+    Ok(entry)
+}
+
+pub async fn update_timetable_entry(
+    executor: impl PgExecutor<'_>,
+    entry: &NewTimeTableEntry,
+) -> Result<Option<TimeTableEntry>> {
+    let sql = r#"UPDATE timetable
+        SET subject_name = $5, subject_type = $6, auditorium = $7, professor = $8,
+            duration_minutes = $9
+        WHERE
+            major_id = $1
+            AND week = $2
+            AND day_of_week = $3
+            AND starts_at = $4
+            AND subgroup IS NOT DISTINCT FROM $10
+        RETURNING *;"#;
+
+    let entry = sqlx::query_as::<_, TimeTableEntry>(sql)
+        .bind(&entry.key.major_id)
+        .bind(entry.key.week)
+        .bind(entry.key.day_of_week)
+        .bind(entry.key.starts_at)
+        .bind(&entry.subject_name)
+        .bind(&entry.subject_type)
+        .bind(&entry.auditorium)
+        .bind(&entry.professor)
+        .bind(entry.duration_minutes)
+        .bind(&entry.key.subgroup)
+        .fetch_optional(executor)
+        .await?;
+
+    Ok(entry)
+}
+
+pub async fn delete_timetable_entry(
+    executor: impl PgExecutor<'_>,
+    key: &TimeTableKey,
+) -> Result<Option<TimeTableEntry>> {
+    let sql = r#"DELETE FROM timetable
+        WHERE
+            major_id = $1
+            AND week = $2
+            AND day_of_week = $3
+            AND starts_at = $4
+            AND subgroup IS NOT DISTINCT FROM $5
+        RETURNING *;"#;
+
+    let entry = sqlx::query_as::<_, TimeTableEntry>(sql)
+        .bind(&key.major_id)
+        .bind(key.week)
+        .bind(key.day_of_week)
+        .bind(key.starts_at)
+        .bind(&key.subgroup)
+        .fetch_optional(executor)
+        .await?;
+
+    Ok(entry)
+}
+
+pub async fn get_major_by_id_opt(
+    executor: impl PgExecutor<'_>,
+    id: &str,
+) -> Result<Option<MajorEntry>> {
+    let sql = r#"SELECT * FROM majors WHERE id = $1;"#;
+
+    let entry = sqlx::query_as::<_, MajorEntry>(sql)
+        .bind(id)
+        .fetch_optional(executor)
+        .await?;
+
+    Ok(entry)
+}
+
+pub async fn get_timetable_by_major(
+    executor: impl PgExecutor<'_>,
+    major_id: &str,
+) -> Result<Vec<TimeTableEntry>> {
+    let sql = r#"SELECT * FROM timetable
+        WHERE major_id = $1
+        ORDER BY week, day_of_week, starts_at;"#;
+
+    let entries = sqlx::query_as::<_, TimeTableEntry>(sql)
+        .bind(major_id)
+        .fetch_all(executor)
+        .await?;
+
+    Ok(entries)
+}
+
+pub async fn upsert_digest_subscription(
+    executor: impl PgExecutor<'_>,
+    subscription: &DigestSubscription,
+) -> Result<DigestSubscription> {
+    let sql = r#"INSERT INTO digest_subscriptions (user_id, send_at, next_day, last_sent_on)
+        VALUES ($1, $2, $3, $4)
+        ON CONFLICT (user_id) DO UPDATE
+            SET send_at = $2, next_day = $3, last_sent_on = $4
+        RETURNING *;"#;
+
+    let entry = sqlx::query_as::<_, DigestSubscription>(sql)
+        .bind(subscription.user_id)
+        .bind(subscription.send_at)
+        .bind(subscription.next_day)
+        .bind(subscription.last_sent_on)
+        .fetch_one(executor)
+        .await?;
+
+    Ok(entry)
+}
+
+pub async fn delete_digest_subscription(
+    executor: impl PgExecutor<'_>,
+    user_id: i64,
+) -> Result<bool> {
+    let sql = r#"DELETE FROM digest_subscriptions WHERE user_id = $1;"#;
+
+    let result = sqlx::query(sql).bind(user_id).execute(executor).await?;
+
+    Ok(result.rows_affected() > 0)
+}
+
+/// Subscriptions which were not sent on `date` and whose time has come.
+pub async fn get_due_digests(
+    executor: impl PgExecutor<'_>,
+    date: NaiveDate,
+    time: NaiveTime,
+) -> Result<Vec<DueDigest>> {
+    let sql = r#"SELECT d.user_id, u.major_id, u.subgroup, u.language, d.next_day
+        FROM digest_subscriptions d
+        JOIN users u ON u.id = d.user_id
+        WHERE
+            d.send_at <= $2
+            AND (d.last_sent_on IS NULL OR d.last_sent_on < $1);"#;
+
+    let entries = sqlx::query_as::<_, DueDigest>(sql)
+        .bind(date)
+        .bind(time)
+        .fetch_all(executor)
+        .await?;
+
+    Ok(entries)
+}
+
+pub async fn mark_digest_sent(
+    executor: impl PgExecutor<'_>,
+    user_id: i64,
+    date: NaiveDate,
+) -> Result<()> {
+    let sql = r#"UPDATE digest_subscriptions SET last_sent_on = $2 WHERE user_id = $1;"#;
+
+    sqlx::query(sql)
+        .bind(user_id)
+        .bind(date)
+        .execute(executor)
+        .await?;
+
+    Ok(())
+}
+
+pub async fn upsert_reminder_setting(
+    executor: impl PgExecutor<'_>,
+    user_id: i64,
+    lead_minutes: i16,
+) -> Result<()> {
+    let sql = r#"INSERT INTO reminder_settings (user_id, lead_minutes) VALUES ($1, $2)
+        ON CONFLICT (user_id) DO UPDATE SET lead_minutes = $2;"#;
+
+    sqlx::query(sql)
+        .bind(user_id)
+        .bind(lead_minutes)
+        .execute(executor)
+        .await?;
+
+    Ok(())
+}
+
+pub async fn delete_reminder_setting(executor: impl PgExecutor<'_>, user_id: i64) -> Result<bool> {
+    let sql = r#"DELETE FROM reminder_settings WHERE user_id = $1;"#;
+
+    let result = sqlx::query(sql).bind(user_id).execute(executor).await?;
+
+    Ok(result.rows_affected() > 0)
+}
+
+pub async fn get_reminder_settings(executor: impl PgExecutor<'_>) -> Result<Vec<ReminderSetting>> {
+    let sql = r#"SELECT r.user_id, u.major_id, u.subgroup, u.language, r.lead_minutes
+        FROM reminder_settings r
+        JOIN users u ON u.id = r.user_id;"#;
+
+    let entries = sqlx::query_as::<_, ReminderSetting>(sql)
+        .fetch_all(executor)
+        .await?;
+
+    Ok(entries)
+}
+
+/// Remembers that a reminder was sent, returns `false` if it had already been sent.
+pub async fn mark_reminder_sent(
+    executor: impl PgExecutor<'_>,
+    user_id: i64,
+    lesson_date: NaiveDate,
+    starts_at: NaiveTime,
+    lesson_id: LessonId,
+) -> Result<bool> {
+    let sql = r#"INSERT INTO sent_reminders (user_id, lesson_date, starts_at, lesson_id)
+        VALUES ($1, $2, $3, $4)
+        ON CONFLICT DO NOTHING;"#;
+
+    let result = sqlx::query(sql)
+        .bind(user_id)
+        .bind(lesson_date)
+        .bind(starts_at)
+        .bind(lesson_id.to_string())
+        .execute(executor)
+        .await?;
+
+    Ok(result.rows_affected() > 0)
+}
+
+pub async fn delete_sent_reminders_before(
+    executor: impl PgExecutor<'_>,
+    lesson_date: NaiveDate,
+) -> Result<()> {
+    let sql = r#"DELETE FROM sent_reminders WHERE lesson_date < $1;"#;
+
+    sqlx::query(sql).bind(lesson_date).execute(executor).await?;
+
+    Ok(())
+}
+
+/// Semesters of the major together with the global ones.
+pub async fn get_semesters_for_major(
+    executor: impl PgExecutor<'_>,
+    major_id: &str,
+) -> Result<Vec<SemesterEntry>> {
+    let sql = r#"SELECT * FROM semesters
+        WHERE major_id = $1 OR major_id IS NULL
+        ORDER BY starts_on;"#;
+
+    let entries = sqlx::query_as::<_, SemesterEntry>(sql)
+        .bind(major_id)
+        .fetch_all(executor)
+        .await?;
+
+    Ok(entries)
+}
+
+pub async fn get_all_semesters(executor: impl PgExecutor<'_>) -> Result<Vec<SemesterEntry>> {
+    let sql = r#"SELECT * FROM semesters ORDER BY starts_on, major_id NULLS FIRST;"#;
+
+    let entries = sqlx::query_as::<_, SemesterEntry>(sql)
+        .fetch_all(executor)
+        .await?;
+
+    Ok(entries)
+}
+
+pub async fn insert_semester(
+    executor: impl PgExecutor<'_>,
+    major_id: Option<&str>,
+    starts_on: NaiveDate,
+    ends_on: NaiveDate,
+    first_week: WeekType,
+) -> Result<SemesterEntry> {
+    let sql = r#"INSERT INTO semesters (major_id, starts_on, ends_on, first_week)
+        VALUES ($1, $2, $3, $4)
+        RETURNING *;"#;
+
+    let entry = sqlx::query_as::<_, SemesterEntry>(sql)
+        .bind(major_id)
+        .bind(starts_on)
+        .bind(ends_on)
+        .bind(first_week)
+        .fetch_one(executor)
+        .await?;
+
+    Ok(entry)
+}
+
+pub async fn delete_semester(executor: impl PgExecutor<'_>, id: i64) -> Result<bool> {
+    let sql = r#"DELETE FROM semesters WHERE id = $1;"#;
+
+    let result = sqlx::query(sql).bind(id).execute(executor).await?;
+
+    Ok(result.rows_affected() > 0)
+}
+
+pub async fn get_lesson_durations(
+    executor: impl PgExecutor<'_>,
+) -> Result<Vec<LessonDurationEntry>> {
+    let sql = r#"SELECT * FROM lesson_durations
+        ORDER BY major_id NULLS FIRST, subject_type NULLS FIRST;"#;
+
+    let entries = sqlx::query_as::<_, LessonDurationEntry>(sql)
+        .fetch_all(executor)
+        .await?;
+
+    Ok(entries)
+}
+
+pub async fn upsert_lesson_duration(
+    executor: impl PgExecutor<'_>,
+    major_id: Option<&str>,
+    subject_type: Option<&str>,
+    duration_minutes: i16,
+) -> Result<LessonDurationEntry> {
+    let sql = r#"INSERT INTO lesson_durations (major_id, subject_type, duration_minutes)
+        VALUES ($1, $2, $3)
+        ON CONFLICT (COALESCE(major_id, ''), COALESCE(subject_type, ''))
+            DO UPDATE SET duration_minutes = $3
+        RETURNING *;"#;
+
+    let entry = sqlx::query_as::<_, LessonDurationEntry>(sql)
+        .bind(major_id)
+        .bind(subject_type)
+        .bind(duration_minutes)
+        .fetch_one(executor)
+        .await?;
+
+    Ok(entry)
+}
+
+pub async fn delete_lesson_duration(executor: impl PgExecutor<'_>, id: i64) -> Result<bool> {
+    let sql = r#"DELETE FROM lesson_durations WHERE id = $1;"#;
+
+    let result = sqlx::query(sql).bind(id).execute(executor).await?;
+
+    Ok(result.rows_affected() > 0)
+}
+
+pub async fn get_timetable_for_day(
+    executor: impl PgExecutor<'_>,
+    major_id: &str,
+    week: WeekType,
+    day_of_week: DayOfWeek,
+) -> Result<Vec<TimeTableEntry>> {
+    let sql = r#"SELECT * FROM public.timetable
+        WHERE
+            week = $1
+            AND day_of_week = $2
+            AND major_id = $3
+        ORDER BY starts_at;"#;
+
+    let entries = sqlx::query_as::<_, TimeTableEntry>(sql)
+        .bind(week)
+        .bind(day_of_week)
+        .bind(major_id)
+        .fetch_all(executor)
+        .await?;
+
+    Ok(entries)
+}
+
+/// Recurring lessons of the majors on the day of both week types, ordered by their start.
+pub async fn get_timetables_for_day(
+    executor: impl PgExecutor<'_>,
+    major_ids: &[String],
+    day_of_week: DayOfWeek,
+) -> Result<Vec<TimeTableEntry>> {
+    let sql = r#"SELECT * FROM public.timetable
+        WHERE
+            day_of_week = $1
+            AND major_id = ANY($2)
+        ORDER BY starts_at;"#;
+
+    let entries = sqlx::query_as::<_, TimeTableEntry>(sql)
+        .bind(day_of_week)
+        .bind(major_ids)
+        .fetch_all(executor)
+        .await?;
+
+    Ok(entries)
+}
+
+pub async fn get_timetable_entries_by_ids(
+    executor: impl PgExecutor<'_>,
+    ids: &[i64],
+) -> Result<Vec<TimeTableEntry>> {
+    let sql = r#"SELECT * FROM timetable WHERE id = ANY($1);"#;
+
+    let entries = sqlx::query_as::<_, TimeTableEntry>(sql)
+        .bind(ids)
+        .fetch_all(executor)
+        .await?;
+
+    Ok(entries)
+}
+
+pub async fn get_professors(executor: impl PgExecutor<'_>) -> Result<Vec<ProfessorEntry>> {
+    let sql = r#"SELECT professor AS name, MIN(id) AS timetable_id FROM timetable
+        WHERE professor IS NOT NULL
+        GROUP BY professor
+        ORDER BY professor;"#;
+
+    let entries = sqlx::query_as::<_, ProfessorEntry>(sql)
+        .fetch_all(executor)
+        .await?;
+
+    Ok(entries)
+}
+
+pub async fn get_majors(executor: impl PgExecutor<'_>) -> Result<Vec<MajorEntry>> {
+    let sql = r#"SELECT * FROM majors ORDER BY id;"#;
+
+    let entries = sqlx::query_as::<_, MajorEntry>(sql)
+        .fetch_all(executor)
+        .await?;
+
+    Ok(entries)
+}
+
+pub async fn get_auditoriums(executor: impl PgExecutor<'_>) -> Result<Vec<String>> {
+    let sql = r#"SELECT DISTINCT auditorium FROM timetable ORDER BY auditorium;"#;
+
+    let auditoriums = sqlx::query_scalar::<_, String>(sql)
+        .fetch_all(executor)
+        .await?;
+
+    Ok(auditoriums)
+}
+
+/// Majors which the professor teaches, including one-off lessons on the date.
+pub async fn get_major_ids_by_professor(
+    executor: impl PgExecutor<'_>,
+    professor: &str,
+    date: NaiveDate,
+) -> Result<Vec<String>> {
+    let sql = r#"SELECT major_id FROM timetable
+            WHERE professor = $1 AND major_id IS NOT NULL
+        UNION
+        SELECT major_id FROM timetable_overrides
+            WHERE kind = 'extra' AND professor = $1 AND date = $2;"#;
+
+    let ids = sqlx::query_scalar::<_, String>(sql)
+        .bind(professor)
+        .bind(date)
+        .fetch_all(executor)
+        .await?;
+
+    Ok(ids)
+}
+
+/// Overrides which change the timetable of the major on the date, including global holidays.
+pub async fn get_overrides_for_date(
+    executor: impl PgExecutor<'_>,
+    major_id: &str,
+    date: NaiveDate,
+) -> Result<Vec<OverrideEntry>> {
+    let sql = r#"SELECT * FROM timetable_overrides
+        WHERE
+            (date = $2 OR new_date = $2)
+            AND (major_id = $1 OR major_id IS NULL);"#;
+
+    let entries = sqlx::query_as::<_, OverrideEntry>(sql)
+        .bind(major_id)
+        .bind(date)
+        .fetch_all(executor)
+        .await?;
+
+    Ok(entries)
+}
+
+/// Overrides of all majors which change the timetable on the date.
+pub async fn get_all_overrides_for_date(
+    executor: impl PgExecutor<'_>,
+    date: NaiveDate,
+) -> Result<Vec<OverrideEntry>> {
+    let sql = r#"SELECT * FROM timetable_overrides WHERE date = $1 OR new_date = $1;"#;
+
+    let entries = sqlx::query_as::<_, OverrideEntry>(sql)
+        .bind(date)
+        .fetch_all(executor)
+        .await?;
+
+    Ok(entries)
+}
+
+pub async fn get_overrides_since(
+    executor: impl PgExecutor<'_>,
+    date: NaiveDate,
+) -> Result<Vec<OverrideEntry>> {
+    let sql = r#"SELECT * FROM timetable_overrides
+        WHERE date >= $1 OR new_date >= $1
+        ORDER BY date, starts_at;"#;
+
+    let entries = sqlx::query_as::<_, OverrideEntry>(sql)
+        .bind(date)
+        .fetch_all(executor)
+        .await?;
+
+    Ok(entries)
+}
+
+/// Stores an override, the `id` of the given entry is ignored.
+pub async fn insert_override(
+    executor: impl PgExecutor<'_>,
+    entry: &OverrideEntry,
+) -> Result<OverrideEntry> {
+    let sql = r#"INSERT INTO timetable_overrides
+        (kind, major_id, timetable_id, date, new_date, starts_at,
+            subject_name, subject_type, auditorium, professor, duration_minutes)
+        VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11)
+        RETURNING *;"#;
+
+    let entry = sqlx::query_as::<_, OverrideEntry>(sql)
+        .bind(entry.kind)
+        .bind(&entry.major_id)
+        .bind(entry.timetable_id)
+        .bind(entry.date)
+        .bind(entry.new_date)
+        .bind(entry.starts_at)
+        .bind(&entry.subject_name)
+        .bind(&entry.subject_type)
+        .bind(&entry.auditorium)
+        .bind(&entry.professor)
+        .bind(entry.duration_minutes)
+        .fetch_one(executor)
+        .await?;
+
+    Ok(entry)
+}
+
+pub async fn delete_override(
+    executor: impl PgExecutor<'_>,
+    id: i64,
+) -> Result<Option<OverrideEntry>> {
+    let sql = r#"DELETE FROM timetable_overrides WHERE id = $1 RETURNING *;"#;
+
+    let entry = sqlx::query_as::<_, OverrideEntry>(sql)
         .bind(id)
         .fetch_optional(executor)
         .await?;
@@ -17,8 +658,213 @@ pub async fn get_user_by_id_opt(
     Ok(entry)
 }
 
-pub async fn get_user_entry_by_id(executor: impl PgExecutor<'_>, id: i64) -> Result<UserEntry> {
-    let entry_opt = get_user_by_id_opt(executor, id).await?;
+/// Ids of users of the major, or of every user if `major_id` is `None`.
+pub async fn get_user_ids_by_major(
+    executor: impl PgExecutor<'_>,
+    major_id: Option<&str>,
+) -> Result<Vec<i64>> {
+    let sql = r#"SELECT id FROM users WHERE $1::text IS NULL OR major_id = $1;"#;
+
+    let ids = sqlx::query_scalar::<_, i64>(sql)
+        .bind(major_id)
+        .fetch_all(executor)
+        .await?;
+
+    Ok(ids)
+}
+
+pub async fn set_chat_major(
+    executor: impl PgExecutor<'_>,
+    chat_id: i64,
+    major_id: &str,
+) -> Result<()> {
+    let sql = r#"INSERT INTO chats (id, major_id) VALUES ($1, $2)
+        ON CONFLICT (id) DO UPDATE SET major_id = $2;"#;
+
+    sqlx::query(sql)
+        .bind(chat_id)
+        .bind(major_id)
+        .execute(executor)
+        .await?;
+
+    Ok(())
+}
+
+/// Major of the user, or the default major of the chat if the user has not set one.
+pub async fn get_major_id_for(
+    executor: impl PgExecutor<'_>,
+    user_id: i64,
+    chat_id: Option<i64>,
+) -> Result<Option<String>> {
+    let sql = r#"SELECT COALESCE(
+            (SELECT major_id FROM users WHERE id = $1),
+            (SELECT major_id FROM chats WHERE id = $2)
+        );"#;
+
+    let major_id = sqlx::query_scalar::<_, Option<String>>(sql)
+        .bind(user_id)
+        .bind(chat_id)
+        .fetch_one(executor)
+        .await?;
+
+    Ok(major_id)
+}
+
+pub async fn set_user_subgroup(
+    executor: impl PgExecutor<'_>,
+    user_id: i64,
+    subgroup: Option<&str>,
+) -> Result<()> {
+    let sql = r#"UPDATE users SET subgroup = $2 WHERE id = $1;"#;
+
+    sqlx::query(sql)
+        .bind(user_id)
+        .bind(subgroup)
+        .execute(executor)
+        .await?;
+
+    Ok(())
+}
+
+pub async fn get_subgroups(
+    executor: impl PgExecutor<'_>,
+    major_id: &str,
+) -> Result<Vec<SubgroupEntry>> {
+    let sql = r#"SELECT * FROM subgroups WHERE major_id = $1 ORDER BY name;"#;
+
+    let entries = sqlx::query_as::<_, SubgroupEntry>(sql)
+        .bind(major_id)
+        .fetch_all(executor)
+        .await?;
+
+    Ok(entries)
+}
+
+pub async fn get_all_subgroups(executor: impl PgExecutor<'_>) -> Result<Vec<SubgroupEntry>> {
+    let sql = r#"SELECT * FROM subgroups ORDER BY major_id, name;"#;
+
+    let entries = sqlx::query_as::<_, SubgroupEntry>(sql)
+        .fetch_all(executor)
+        .await?;
+
+    Ok(entries)
+}
+
+/// Returns `false` if the major already has such a subgroup.
+pub async fn insert_subgroup(
+    executor: impl PgExecutor<'_>,
+    major_id: &str,
+    name: &str,
+) -> Result<bool> {
+    let sql = r#"INSERT INTO subgroups (major_id, name) VALUES ($1, $2) ON CONFLICT DO NOTHING;"#;
+
+    let result = sqlx::query(sql)
+        .bind(major_id)
+        .bind(name)
+        .execute(executor)
+        .await?;
+
+    Ok(result.rows_affected() > 0)
+}
+
+/// Deletes the subgroup together with its lessons, members keep the major.
+pub async fn delete_subgroup(
+    executor: impl PgExecutor<'_>,
+    major_id: &str,
+    name: &str,
+) -> Result<bool> {
+    let sql = r#"WITH members AS (
+            UPDATE users SET subgroup = NULL WHERE major_id = $1 AND subgroup = $2
+        )
+        DELETE FROM subgroups WHERE major_id = $1 AND name = $2;"#;
+
+    let result = sqlx::query(sql)
+        .bind(major_id)
+        .bind(name)
+        .execute(executor)
+        .await?;
+
+    Ok(result.rows_affected() > 0)
+}
+
+pub async fn get_electives(
+    executor: impl PgExecutor<'_>,
+    user_id: i64,
+) -> Result<Vec<ElectiveEntry>> {
+    let sql = r#"SELECT * FROM electives WHERE user_id = $1 ORDER BY major_id, subject_name;"#;
+
+    let entries = sqlx::query_as::<_, ElectiveEntry>(sql)
+        .bind(user_id)
+        .fetch_all(executor)
+        .await?;
+
+    Ok(entries)
+}
+
+/// Electives of the users who are reminded about lessons.
+pub async fn get_reminded_electives(executor: impl PgExecutor<'_>) -> Result<Vec<ElectiveEntry>> {
+    let sql = r#"SELECT e.* FROM electives e
+        JOIN reminder_settings r ON r.user_id = e.user_id;"#;
+
+    let entries = sqlx::query_as::<_, ElectiveEntry>(sql)
+        .fetch_all(executor)
+        .await?;
+
+    Ok(entries)
+}
+
+/// Returns `false` if the user already attends the subject.
+pub async fn insert_elective(
+    executor: impl PgExecutor<'_>,
+    user_id: i64,
+    major_id: &str,
+    subject_name: &str,
+) -> Result<bool> {
+    let sql = r#"INSERT INTO electives (user_id, major_id, subject_name) VALUES ($1, $2, $3)
+        ON CONFLICT DO NOTHING;"#;
+
+    let result = sqlx::query(sql)
+        .bind(user_id)
+        .bind(major_id)
+        .bind(subject_name)
+        .execute(executor)
+        .await?;
+
+    Ok(result.rows_affected() > 0)
+}
+
+pub async fn delete_elective(
+    executor: impl PgExecutor<'_>,
+    user_id: i64,
+    major_id: &str,
+    subject_name: &str,
+) -> Result<bool> {
+    let sql =
+        r#"DELETE FROM electives WHERE user_id = $1 AND major_id = $2 AND subject_name = $3;"#;
+
+    let result = sqlx::query(sql)
+        .bind(user_id)
+        .bind(major_id)
+        .bind(subject_name)
+        .execute(executor)
+        .await?;
+
+    Ok(result.rows_affected() > 0)
+}
+
+/// One lesson of every subject of the major, so that the subject can be referred to by its id.
+pub async fn get_subjects_by_major(
+    executor: impl PgExecutor<'_>,
+    major_id: &str,
+) -> Result<Vec<TimeTableEntry>> {
+    let sql = r#"SELECT DISTINCT ON (subject_name) * FROM timetable
+        WHERE major_id = $1
+        ORDER BY subject_name, id;"#;
+
+    let entries = sqlx::query_as::<_, TimeTableEntry>(sql)
+        .bind(major_id)
+        .fetch_all(executor)
+        .await?;
 
-    entry_opt.ok_or(anyhow!("user entry not found"))
+    Ok(entries)
 }
@@ -1,8 +1,35 @@
-use chrono::{DateTime, Datelike, FixedOffset, NaiveTime, Weekday};
+use std::fmt;
+
+use chrono::{DateTime, Datelike, Duration, FixedOffset, NaiveDate, NaiveTime, Weekday};
+use serde::Deserialize;
 use sqlx::FromRow;
+use strum::EnumString;
+
+use crate::utils::{i18n::Language, time::Week};
+
+/// Duration of a lesson if no default is configured in `lesson_durations`.
+pub const DEFAULT_LESSON_DURATION_MINUTES: i16 = 90;
 
-#[derive(Debug, sqlx::Type)]
+/// Longest lesson, mirrors the `CHECK` constraints in `migrations/`.
+pub const MAX_LESSON_DURATION_MINUTES: i16 = 720;
+
+#[derive(
+    Debug,
+    Clone,
+    Copy,
+    PartialEq,
+    Eq,
+    Hash,
+    PartialOrd,
+    Ord,
+    sqlx::Type,
+    Deserialize,
+    EnumString,
+    strum::Display,
+)]
 #[sqlx(type_name = "day_type", rename_all = "lowercase")]
+#[serde(rename_all = "lowercase")]
+#[strum(serialize_all = "lowercase")]
 pub enum DayOfWeek {
     Monday,
     Tuesday,
@@ -41,51 +68,311 @@ impl From<DayOfWeek> for Weekday {
     }
 }
 
-#[derive(Debug, sqlx::Type)]
+#[derive(
+    Debug,
+    Clone,
+    Copy,
+    PartialEq,
+    Eq,
+    Hash,
+    PartialOrd,
+    Ord,
+    sqlx::Type,
+    Deserialize,
+    EnumString,
+    strum::Display,
+)]
 #[sqlx(type_name = "week_type", rename_all = "lowercase")]
+#[serde(rename_all = "lowercase")]
+#[strum(serialize_all = "lowercase")]
 pub enum WeekType {
     Odd,
     Even,
 }
 
-impl From<DateTime<FixedOffset>> for WeekType {
-    fn from(val: DateTime<FixedOffset>) -> Self {
-        let num = val.iso_week().week();
-
-        match num % 2 {
+impl WeekType {
+    /// Week type by the parity of the ISO week, used when no semesters are configured.
+    pub fn from_iso_week(date: NaiveDate) -> Self {
+        match date.iso_week().week() % 2 {
             0 => WeekType::Even,
             _ => WeekType::Odd,
         }
     }
+
+    pub fn opposite(self) -> Self {
+        match self {
+            WeekType::Odd => WeekType::Even,
+            WeekType::Even => WeekType::Odd,
+        }
+    }
 }
 
-#[derive(Debug, FromRow)]
+impl From<DateTime<FixedOffset>> for WeekType {
+    fn from(val: DateTime<FixedOffset>) -> Self {
+        WeekType::from_iso_week(val.date_naive())
+    }
+}
+
+#[allow(dead_code)]
+#[derive(Debug, Clone, FromRow)]
 pub struct MajorEntry {
     pub id: String,
     pub title: String,
     pub enrollment_year: i16,
 }
 
-#[derive(Debug, FromRow)]
+#[allow(dead_code)]
+#[derive(Debug, Clone, FromRow)]
 pub struct TimeTableEntry {
     pub id: i64,
     pub major_id: Option<String>,
     pub week: WeekType,
     pub day_of_week: DayOfWeek,
     pub starts_at: NaiveTime,
+    pub duration_minutes: i16,
     pub ends_at: NaiveTime,
     pub subject_name: String,
     pub subject_type: String,
     pub auditorium: String,
     pub professor: Option<String>,
+    /// `None` if the lesson is for the whole major.
+    pub subgroup: Option<String>,
+    /// Not a column, the date from which the lesson was moved by an override.
+    #[sqlx(default)]
+    pub moved_from: Option<NaiveDate>,
+    /// Not a column, set for lessons of another major which the user attends as an elective.
+    #[sqlx(default)]
+    pub elective: bool,
+    /// Not a column, set for one-off lessons, `id` is then the id of the override.
+    #[sqlx(default)]
+    pub extra: bool,
 }
 
-#[derive(Debug, FromRow)]
+impl TimeTableEntry {
+    pub fn lesson_id(&self) -> LessonId {
+        if self.extra {
+            LessonId::Extra(self.id)
+        } else {
+            LessonId::Timetable(self.id)
+        }
+    }
+
+    pub fn key(&self) -> TimeTableKey {
+        TimeTableKey {
+            major_id: self.major_id.clone().unwrap_or_default(),
+            subgroup: self.subgroup.clone(),
+            week: self.week,
+            day_of_week: self.day_of_week,
+            starts_at: self.starts_at,
+        }
+    }
+
+    /// Lessons of the whole major are for everyone,
+    /// users who have not picked a subgroup see the lessons of every subgroup.
+    pub fn is_for_subgroup(&self, subgroup: Option<&str>) -> bool {
+        match (self.subgroup.as_deref(), subgroup) {
+            (None, _) | (_, None) => true,
+            (Some(own), Some(subgroup)) => own == subgroup,
+        }
+    }
+}
+
+/// Identifies a lesson, one-off lessons are overrides and their ids may clash with timetable ids.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum LessonId {
+    Timetable(i64),
+    Extra(i64),
+}
+
+impl fmt::Display for LessonId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LessonId::Timetable(id) => write!(f, "timetable-{id}"),
+            LessonId::Extra(id) => write!(f, "extra-{id}"),
+        }
+    }
+}
+
+/// Identifies a single row of `timetable`, mirrors its `timetable_slot` unique index.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct TimeTableKey {
+    pub major_id: String,
+    pub subgroup: Option<String>,
+    pub week: WeekType,
+    pub day_of_week: DayOfWeek,
+    pub starts_at: NaiveTime,
+}
+
+/// A `timetable` row which is not yet stored in the database.
+#[derive(Debug, Clone)]
+pub struct NewTimeTableEntry {
+    pub key: TimeTableKey,
+    pub subject_name: String,
+    pub subject_type: String,
+    pub auditorium: String,
+    pub professor: Option<String>,
+    pub duration_minutes: i16,
+}
+
+impl NewTimeTableEntry {
+    /// Builds an unsaved entry which can be rendered by `format_entry`.
+    pub fn preview(&self) -> TimeTableEntry {
+        TimeTableEntry {
+            id: 0,
+            major_id: Some(self.key.major_id.clone()),
+            week: self.key.week,
+            day_of_week: self.key.day_of_week,
+            starts_at: self.key.starts_at,
+            duration_minutes: self.duration_minutes,
+            ends_at: self.key.starts_at + Duration::minutes(self.duration_minutes.into()),
+            subject_name: self.subject_name.clone(),
+            subject_type: self.subject_type.clone(),
+            auditorium: self.auditorium.clone(),
+            professor: self.professor.clone(),
+            subgroup: self.key.subgroup.clone(),
+            moved_from: None,
+            elective: false,
+            extra: false,
+        }
+    }
+}
+
+#[derive(Debug, Clone, FromRow)]
 pub struct UserEntry {
     pub id: i64,
     pub major_id: String,
+    pub subgroup: Option<String>,
+}
+
+/// A subject of another major which the user attends, all of its lessons are included.
+#[derive(Debug, Clone, FromRow)]
+pub struct ElectiveEntry {
+    pub user_id: i64,
+    pub major_id: String,
+    pub subject_name: String,
+}
+
+#[derive(Debug, Clone, FromRow)]
+pub struct SubgroupEntry {
+    pub major_id: String,
+    pub name: String,
+}
+
+#[derive(Debug, Clone, FromRow)]
+pub struct SemesterEntry {
+    pub id: i64,
+    pub major_id: Option<String>,
+    pub starts_on: NaiveDate,
+    pub ends_on: NaiveDate,
+    pub first_week: WeekType,
+}
+
+impl SemesterEntry {
+    pub fn contains(&self, date: NaiveDate) -> bool {
+        self.starts_on <= date && date <= self.ends_on
+    }
+
+    /// Week type of the date, counting weeks from the one the semester starts on.
+    pub fn week_type(&self, date: NaiveDate) -> Option<WeekType> {
+        if !self.contains(date) {
+            return None;
+        }
+
+        let first_monday = Week::containing(self.starts_on).monday();
+        let weeks = (date - first_monday).num_days() / 7;
+
+        match weeks % 2 {
+            0 => Some(self.first_week),
+            _ => Some(self.first_week.opposite()),
+        }
+    }
+}
+
+#[derive(Debug, Clone, FromRow)]
+pub struct DigestSubscription {
+    pub user_id: i64,
+    pub send_at: NaiveTime,
+    pub next_day: bool,
+    pub last_sent_on: Option<NaiveDate>,
+}
+
+/// A subscription which is due to be sent, joined with the user's major.
+#[derive(Debug, FromRow)]
+pub struct DueDigest {
+    pub user_id: i64,
+    pub major_id: String,
+    pub subgroup: Option<String>,
+    pub language: Language,
+    pub next_day: bool,
+}
+
+/// Lead time of the reminders, joined with the user's major.
+#[derive(Debug, FromRow)]
+pub struct ReminderSetting {
+    pub user_id: i64,
+    pub major_id: String,
+    pub subgroup: Option<String>,
+    pub language: Language,
+    pub lead_minutes: i16,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, sqlx::Type, EnumString, strum::Display)]
+#[sqlx(type_name = "override_kind", rename_all = "lowercase")]
+#[strum(serialize_all = "lowercase")]
+pub enum OverrideKind {
+    Cancel,
+    Move,
+    Extra,
+    Holiday,
+}
+
+/// A one-off change of the timetable on a specific date.
+#[derive(Debug, Clone, FromRow)]
+pub struct OverrideEntry {
+    pub id: i64,
+    pub kind: OverrideKind,
+    pub major_id: Option<String>,
+    pub timetable_id: Option<i64>,
+    pub date: NaiveDate,
+    pub new_date: Option<NaiveDate>,
+    pub starts_at: Option<NaiveTime>,
+    pub subject_name: Option<String>,
+    pub subject_type: Option<String>,
+    pub auditorium: Option<String>,
+    pub professor: Option<String>,
+    pub duration_minutes: Option<i16>,
+}
+
+/// Default duration of new lessons, `None` major or subject type matches any.
+#[derive(Debug, Clone, FromRow)]
+pub struct LessonDurationEntry {
+    pub id: i64,
+    pub major_id: Option<String>,
+    pub subject_type: Option<String>,
+    pub duration_minutes: i16,
+}
+
+impl LessonDurationEntry {
+    pub fn matches(&self, major_id: &str, subject_type: &str) -> bool {
+        self.major_id
+            .as_deref()
+            .map_or(true, |value| value == major_id)
+            && self
+                .subject_type
+                .as_deref()
+                .map_or(true, |value| value == subject_type)
+    }
+}
+
+/// A distinct professor together with one of their lessons,
+/// the lesson id is short enough to identify the professor in callback data.
+#[derive(Debug, Clone, FromRow)]
+pub struct ProfessorEntry {
+    pub name: String,
+    pub timetable_id: i64,
 }
 
+#[allow(dead_code)]
 #[derive(Debug, FromRow)]
 pub struct Exists {
     pub exists: bool,
@@ -0,0 +1,44 @@
+//! Values and rows shared by the tests.
+
+use chrono::{NaiveDate, NaiveTime};
+
+use crate::utils::sql::types::{
+    DayOfWeek, NewTimeTableEntry, TimeTableEntry, TimeTableKey, WeekType,
+};
+
+pub fn date(year: i32, month: u32, day: u32) -> NaiveDate {
+    NaiveDate::from_ymd_opt(year, month, day).unwrap()
+}
+
+pub fn time(hour: u32) -> NaiveTime {
+    NaiveTime::from_hms_opt(hour, 0, 0).unwrap()
+}
+
+/// A 90 minute lecture of `ivt` in 301 on odd weeks.
+pub fn entry(day_of_week: DayOfWeek, hour: u32, subject_name: &str) -> NewTimeTableEntry {
+    NewTimeTableEntry {
+        key: TimeTableKey {
+            major_id: "ivt".to_owned(),
+            subgroup: None,
+            week: WeekType::Odd,
+            day_of_week,
+            starts_at: time(hour),
+        },
+        subject_name: subject_name.to_owned(),
+        subject_type: "Лекция".to_owned(),
+        auditorium: "301".to_owned(),
+        professor: None,
+        duration_minutes: 90,
+    }
+}
+
+/// A stored lecture of the major on odd Thursdays, otherwise the same as [`entry`].
+pub fn lesson(id: i64, major_id: &str, hour: u32, subject_name: &str) -> TimeTableEntry {
+    let mut entry = entry(DayOfWeek::Thursday, hour, subject_name);
+    entry.key.major_id = major_id.to_owned();
+
+    TimeTableEntry {
+        id,
+        ..entry.preview()
+    }
+}
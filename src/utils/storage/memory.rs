@@ -0,0 +1,792 @@
+use std::{
+    collections::{HashMap, HashSet},
+    sync::{Mutex, MutexGuard},
+};
+
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use chrono::{NaiveDate, NaiveTime};
+
+use crate::utils::{
+    i18n::Language,
+    import::TimetableDiff,
+    sql::types::{
+        DayOfWeek, DigestSubscription, DueDigest, ElectiveEntry, LessonDurationEntry, LessonId,
+        MajorEntry, NewTimeTableEntry, OverrideEntry, OverrideKind, ProfessorEntry,
+        ReminderSetting, SemesterEntry, SubgroupEntry, TimeTableEntry, TimeTableKey, UserEntry,
+        WeekType,
+    },
+};
+
+use super::Storage;
+
+/// A row of `users`, users who only picked a language have no major.
+#[derive(Debug, Clone, Default)]
+pub struct MemoryUser {
+    pub major_id: Option<String>,
+    pub subgroup: Option<String>,
+    pub language: Language,
+}
+
+/// Tables of the storage, filled by tests directly.
+#[derive(Debug, Default)]
+pub struct State {
+    pub majors: Vec<MajorEntry>,
+    pub users: HashMap<i64, MemoryUser>,
+    pub chats: HashMap<i64, String>,
+    pub subgroups: Vec<SubgroupEntry>,
+    pub electives: Vec<ElectiveEntry>,
+    pub timetable: Vec<TimeTableEntry>,
+    pub semesters: Vec<SemesterEntry>,
+    pub overrides: Vec<OverrideEntry>,
+    pub lesson_durations: Vec<LessonDurationEntry>,
+    pub digests: Vec<DigestSubscription>,
+    /// Lead time of the reminders by the user.
+    pub reminders: HashMap<i64, i16>,
+    pub sent_reminders: HashSet<(i64, NaiveDate, NaiveTime, LessonId)>,
+}
+
+/// The storage which keeps everything in memory and mirrors the queries of `PgStorage`.
+#[derive(Debug, Default)]
+pub struct MemoryStorage {
+    state: Mutex<State>,
+}
+
+impl MemoryStorage {
+    pub fn new(state: State) -> Self {
+        Self {
+            state: Mutex::new(state),
+        }
+    }
+
+    pub fn state(&self) -> MutexGuard<'_, State> {
+        self.state.lock().unwrap()
+    }
+}
+
+/// The id of a new row, like a `bigserial` column would give.
+fn next_id(ids: impl Iterator<Item = i64>) -> i64 {
+    ids.max().unwrap_or_default() + 1
+}
+
+fn user_entry(id: i64, user: &MemoryUser) -> Option<UserEntry> {
+    Some(UserEntry {
+        id,
+        major_id: user.major_id.clone()?,
+        subgroup: user.subgroup.clone(),
+    })
+}
+
+#[async_trait]
+impl Storage for MemoryStorage {
+    async fn get_major(&self, id: &str) -> Result<Option<MajorEntry>> {
+        let state = self.state();
+        Ok(state.majors.iter().find(|major| major.id == id).cloned())
+    }
+
+    async fn get_majors(&self) -> Result<Vec<MajorEntry>> {
+        let mut majors = self.state().majors.clone();
+        majors.sort_by(|a, b| a.id.cmp(&b.id));
+        Ok(majors)
+    }
+
+    async fn get_user(&self, id: i64) -> Result<Option<UserEntry>> {
+        let state = self.state();
+        Ok(state.users.get(&id).and_then(|user| user_entry(id, user)))
+    }
+
+    async fn set_user_major(&self, id: i64, major_id: &str) -> Result<UserEntry> {
+        let mut state = self.state();
+        let user = state.users.entry(id).or_default();
+
+        user.major_id = Some(major_id.to_owned());
+        user.subgroup = None;
+
+        user_entry(id, user).ok_or(anyhow!("the major of #{id} is not set"))
+    }
+
+    async fn get_user_language(&self, id: i64) -> Result<Language> {
+        let state = self.state();
+        Ok(state
+            .users
+            .get(&id)
+            .map(|user| user.language)
+            .unwrap_or_default())
+    }
+
+    async fn set_user_language(&self, id: i64, language: Language) -> Result<()> {
+        self.state().users.entry(id).or_default().language = language;
+        Ok(())
+    }
+
+    async fn set_user_subgroup(&self, id: i64, subgroup: Option<&str>) -> Result<()> {
+        if let Some(user) = self.state().users.get_mut(&id) {
+            user.subgroup = subgroup.map(str::to_owned);
+        }
+
+        Ok(())
+    }
+
+    async fn get_user_ids_by_major(&self, major_id: Option<&str>) -> Result<Vec<i64>> {
+        Ok(self
+            .state()
+            .users
+            .iter()
+            .filter(|(_, user)| major_id.is_none() || user.major_id.as_deref() == major_id)
+            .map(|(id, _)| *id)
+            .collect())
+    }
+
+    async fn get_major_id_for(&self, user_id: i64, chat_id: Option<i64>) -> Result<Option<String>> {
+        let state = self.state();
+
+        let own = state
+            .users
+            .get(&user_id)
+            .and_then(|user| user.major_id.clone());
+        let chat = chat_id.and_then(|chat_id| state.chats.get(&chat_id).cloned());
+
+        Ok(own.or(chat))
+    }
+
+    async fn set_chat_major(&self, chat_id: i64, major_id: &str) -> Result<()> {
+        self.state().chats.insert(chat_id, major_id.to_owned());
+        Ok(())
+    }
+
+    async fn get_subgroups(&self, major_id: &str) -> Result<Vec<SubgroupEntry>> {
+        let mut subgroups = self
+            .state()
+            .subgroups
+            .iter()
+            .filter(|subgroup| subgroup.major_id == major_id)
+            .cloned()
+            .collect::<Vec<_>>();
+
+        subgroups.sort_by(|a, b| a.name.cmp(&b.name));
+
+        Ok(subgroups)
+    }
+
+    async fn get_all_subgroups(&self) -> Result<Vec<SubgroupEntry>> {
+        let mut subgroups = self.state().subgroups.clone();
+
+        subgroups.sort_by(|a, b| (&a.major_id, &a.name).cmp(&(&b.major_id, &b.name)));
+
+        Ok(subgroups)
+    }
+
+    async fn insert_subgroup(&self, major_id: &str, name: &str) -> Result<bool> {
+        let mut state = self.state();
+
+        let exists = state
+            .subgroups
+            .iter()
+            .any(|subgroup| subgroup.major_id == major_id && subgroup.name == name);
+
+        if !exists {
+            state.subgroups.push(SubgroupEntry {
+                major_id: major_id.to_owned(),
+                name: name.to_owned(),
+            });
+        }
+
+        Ok(!exists)
+    }
+
+    async fn delete_subgroup(&self, major_id: &str, name: &str) -> Result<bool> {
+        let mut state = self.state();
+        let count = state.subgroups.len();
+
+        state
+            .subgroups
+            .retain(|subgroup| !(subgroup.major_id == major_id && subgroup.name == name));
+
+        if state.subgroups.len() == count {
+            return Ok(false);
+        }
+
+        state.timetable.retain(|entry| {
+            !(entry.major_id.as_deref() == Some(major_id)
+                && entry.subgroup.as_deref() == Some(name))
+        });
+
+        for user in state.users.values_mut() {
+            if user.major_id.as_deref() == Some(major_id) && user.subgroup.as_deref() == Some(name)
+            {
+                user.subgroup = None;
+            }
+        }
+
+        Ok(true)
+    }
+
+    async fn get_electives(&self, user_id: i64) -> Result<Vec<ElectiveEntry>> {
+        let mut electives = self
+            .state()
+            .electives
+            .iter()
+            .filter(|elective| elective.user_id == user_id)
+            .cloned()
+            .collect::<Vec<_>>();
+
+        electives
+            .sort_by(|a, b| (&a.major_id, &a.subject_name).cmp(&(&b.major_id, &b.subject_name)));
+
+        Ok(electives)
+    }
+
+    async fn insert_elective(
+        &self,
+        user_id: i64,
+        major_id: &str,
+        subject_name: &str,
+    ) -> Result<bool> {
+        let mut state = self.state();
+
+        let exists = state.electives.iter().any(|elective| {
+            elective.user_id == user_id
+                && elective.major_id == major_id
+                && elective.subject_name == subject_name
+        });
+
+        if !exists {
+            state.electives.push(ElectiveEntry {
+                user_id,
+                major_id: major_id.to_owned(),
+                subject_name: subject_name.to_owned(),
+            });
+        }
+
+        Ok(!exists)
+    }
+
+    async fn delete_elective(
+        &self,
+        user_id: i64,
+        major_id: &str,
+        subject_name: &str,
+    ) -> Result<bool> {
+        let mut state = self.state();
+        let count = state.electives.len();
+
+        state.electives.retain(|elective| {
+            !(elective.user_id == user_id
+                && elective.major_id == major_id
+                && elective.subject_name == subject_name)
+        });
+
+        Ok(state.electives.len() < count)
+    }
+
+    async fn get_timetable_by_major(&self, major_id: &str) -> Result<Vec<TimeTableEntry>> {
+        let mut entries = self
+            .state()
+            .timetable
+            .iter()
+            .filter(|entry| entry.major_id.as_deref() == Some(major_id))
+            .cloned()
+            .collect::<Vec<_>>();
+
+        entries.sort_by_key(|entry| (entry.week, entry.day_of_week, entry.starts_at));
+
+        Ok(entries)
+    }
+
+    async fn get_timetable_for_day(
+        &self,
+        major_id: &str,
+        week: WeekType,
+        day_of_week: DayOfWeek,
+    ) -> Result<Vec<TimeTableEntry>> {
+        let mut entries = self
+            .get_timetable_by_major(major_id)
+            .await?
+            .into_iter()
+            .filter(|entry| entry.week == week && entry.day_of_week == day_of_week)
+            .collect::<Vec<_>>();
+
+        entries.sort_by_key(|entry| entry.starts_at);
+
+        Ok(entries)
+    }
+
+    async fn get_timetables_for_day(
+        &self,
+        major_ids: &[String],
+        day_of_week: DayOfWeek,
+    ) -> Result<Vec<TimeTableEntry>> {
+        let mut entries = self
+            .state()
+            .timetable
+            .iter()
+            .filter(|entry| entry.day_of_week == day_of_week)
+            .filter(
+                |entry| matches!(&entry.major_id, Some(major_id) if major_ids.contains(major_id)),
+            )
+            .cloned()
+            .collect::<Vec<_>>();
+
+        entries.sort_by_key(|entry| entry.starts_at);
+
+        Ok(entries)
+    }
+
+    async fn get_timetable_entries_by_ids(&self, ids: &[i64]) -> Result<Vec<TimeTableEntry>> {
+        Ok(self
+            .state()
+            .timetable
+            .iter()
+            .filter(|entry| ids.contains(&entry.id))
+            .cloned()
+            .collect())
+    }
+
+    async fn get_timetable_entry_by_key(
+        &self,
+        key: &TimeTableKey,
+    ) -> Result<Option<TimeTableEntry>> {
+        Ok(self
+            .state()
+            .timetable
+            .iter()
+            .find(|entry| entry.key() == *key)
+            .cloned())
+    }
+
+    async fn insert_timetable_entry(&self, entry: &NewTimeTableEntry) -> Result<TimeTableEntry> {
+        let mut state = self.state();
+
+        if state.timetable.iter().any(|other| other.key() == entry.key) {
+            return Err(anyhow!("the slot of the lesson is already taken"));
+        }
+
+        let entry = TimeTableEntry {
+            id: next_id(state.timetable.iter().map(|entry| entry.id)),
+            ..entry.preview()
+        };
+        state.timetable.push(entry.clone());
+
+        Ok(entry)
+    }
+
+    async fn update_timetable_entry(
+        &self,
+        entry: &NewTimeTableEntry,
+    ) -> Result<Option<(TimeTableEntry, TimeTableEntry)>> {
+        let mut state = self.state();
+
+        let Some(stored) = state
+            .timetable
+            .iter_mut()
+            .find(|stored| stored.key() == entry.key)
+        else {
+            return Ok(None);
+        };
+
+        let old = stored.clone();
+        *stored = TimeTableEntry {
+            id: old.id,
+            ..entry.preview()
+        };
+
+        Ok(Some((old, stored.clone())))
+    }
+
+    async fn delete_timetable_entry(&self, key: &TimeTableKey) -> Result<Option<TimeTableEntry>> {
+        let mut state = self.state();
+
+        let index = state.timetable.iter().position(|entry| entry.key() == *key);
+
+        Ok(index.map(|index| state.timetable.remove(index)))
+    }
+
+    async fn import_timetable(
+        &self,
+        major_id: &str,
+        entries: Vec<NewTimeTableEntry>,
+    ) -> Result<TimetableDiff> {
+        let existing = self.get_timetable_by_major(major_id).await?;
+        let diff = TimetableDiff::new(existing, entries);
+
+        for entry in &diff.removed {
+            self.delete_timetable_entry(&entry.key()).await?;
+        }
+
+        for (_, entry) in &diff.changed {
+            self.update_timetable_entry(entry).await?;
+        }
+
+        for entry in &diff.added {
+            self.insert_timetable_entry(entry).await?;
+        }
+
+        Ok(diff)
+    }
+
+    async fn get_subjects_by_major(&self, major_id: &str) -> Result<Vec<TimeTableEntry>> {
+        let mut entries = self.get_timetable_by_major(major_id).await?;
+
+        entries.sort_by(|a, b| (&a.subject_name, a.id).cmp(&(&b.subject_name, b.id)));
+        entries.dedup_by(|a, b| a.subject_name == b.subject_name);
+
+        Ok(entries)
+    }
+
+    async fn get_semesters_for_major(&self, major_id: &str) -> Result<Vec<SemesterEntry>> {
+        let mut semesters = self
+            .state()
+            .semesters
+            .iter()
+            .filter(|semester| semester.major_id.as_deref().unwrap_or(major_id) == major_id)
+            .cloned()
+            .collect::<Vec<_>>();
+
+        semesters.sort_by_key(|semester| semester.starts_on);
+
+        Ok(semesters)
+    }
+
+    async fn get_all_semesters(&self) -> Result<Vec<SemesterEntry>> {
+        let mut semesters = self.state().semesters.clone();
+
+        semesters.sort_by_key(|semester| (semester.starts_on, semester.major_id.is_some()));
+
+        Ok(semesters)
+    }
+
+    async fn insert_semester(
+        &self,
+        major_id: Option<&str>,
+        starts_on: NaiveDate,
+        ends_on: NaiveDate,
+        first_week: WeekType,
+    ) -> Result<SemesterEntry> {
+        let mut state = self.state();
+
+        let semester = SemesterEntry {
+            id: next_id(state.semesters.iter().map(|semester| semester.id)),
+            major_id: major_id.map(str::to_owned),
+            starts_on,
+            ends_on,
+            first_week,
+        };
+        state.semesters.push(semester.clone());
+
+        Ok(semester)
+    }
+
+    async fn delete_semester(&self, id: i64) -> Result<bool> {
+        let mut state = self.state();
+        let count = state.semesters.len();
+
+        state.semesters.retain(|semester| semester.id != id);
+
+        Ok(state.semesters.len() < count)
+    }
+
+    async fn get_lesson_durations(&self) -> Result<Vec<LessonDurationEntry>> {
+        let mut durations = self.state().lesson_durations.clone();
+
+        durations.sort_by(|a, b| {
+            let key = |entry: &LessonDurationEntry| {
+                (
+                    entry.major_id.is_some(),
+                    entry.major_id.clone(),
+                    entry.subject_type.is_some(),
+                    entry.subject_type.clone(),
+                )
+            };
+            key(a).cmp(&key(b))
+        });
+
+        Ok(durations)
+    }
+
+    async fn upsert_lesson_duration(
+        &self,
+        major_id: Option<&str>,
+        subject_type: Option<&str>,
+        duration_minutes: i16,
+    ) -> Result<LessonDurationEntry> {
+        let mut state = self.state();
+        let id = next_id(state.lesson_durations.iter().map(|entry| entry.id));
+
+        let existing = state.lesson_durations.iter_mut().find(|entry| {
+            entry.major_id.as_deref() == major_id && entry.subject_type.as_deref() == subject_type
+        });
+
+        let entry = match existing {
+            Some(entry) => {
+                entry.duration_minutes = duration_minutes;
+                entry.clone()
+            }
+            None => {
+                let entry = LessonDurationEntry {
+                    id,
+                    major_id: major_id.map(str::to_owned),
+                    subject_type: subject_type.map(str::to_owned),
+                    duration_minutes,
+                };
+                state.lesson_durations.push(entry.clone());
+                entry
+            }
+        };
+
+        Ok(entry)
+    }
+
+    async fn delete_lesson_duration(&self, id: i64) -> Result<bool> {
+        let mut state = self.state();
+        let count = state.lesson_durations.len();
+
+        state.lesson_durations.retain(|entry| entry.id != id);
+
+        Ok(state.lesson_durations.len() < count)
+    }
+
+    async fn get_overrides_for_date(
+        &self,
+        major_id: &str,
+        date: NaiveDate,
+    ) -> Result<Vec<OverrideEntry>> {
+        Ok(self
+            .state()
+            .overrides
+            .iter()
+            .filter(|entry| entry.date == date || entry.new_date == Some(date))
+            .filter(|entry| entry.major_id.as_deref().unwrap_or(major_id) == major_id)
+            .cloned()
+            .collect())
+    }
+
+    async fn get_all_overrides_for_date(&self, date: NaiveDate) -> Result<Vec<OverrideEntry>> {
+        Ok(self
+            .state()
+            .overrides
+            .iter()
+            .filter(|entry| entry.date == date || entry.new_date == Some(date))
+            .cloned()
+            .collect())
+    }
+
+    async fn get_overrides_since(&self, date: NaiveDate) -> Result<Vec<OverrideEntry>> {
+        let mut overrides = self
+            .state()
+            .overrides
+            .iter()
+            .filter(|entry| entry.date >= date || entry.new_date.map_or(false, |new| new >= date))
+            .cloned()
+            .collect::<Vec<_>>();
+
+        overrides.sort_by_key(|entry| (entry.date, entry.starts_at));
+
+        Ok(overrides)
+    }
+
+    async fn insert_override(&self, entry: &OverrideEntry) -> Result<OverrideEntry> {
+        let mut state = self.state();
+
+        let entry = OverrideEntry {
+            id: next_id(state.overrides.iter().map(|entry| entry.id)),
+            ..entry.clone()
+        };
+        state.overrides.push(entry.clone());
+
+        Ok(entry)
+    }
+
+    async fn delete_override(&self, id: i64) -> Result<Option<OverrideEntry>> {
+        let mut state = self.state();
+
+        let index = state.overrides.iter().position(|entry| entry.id == id);
+
+        Ok(index.map(|index| state.overrides.remove(index)))
+    }
+
+    async fn get_professors(&self) -> Result<Vec<ProfessorEntry>> {
+        let mut professors: Vec<ProfessorEntry> = vec![];
+
+        for entry in self.state().timetable.iter() {
+            let Some(name) = entry.professor.as_ref() else {
+                continue;
+            };
+
+            match professors
+                .iter_mut()
+                .find(|professor| &professor.name == name)
+            {
+                Some(professor) => professor.timetable_id = professor.timetable_id.min(entry.id),
+                None => professors.push(ProfessorEntry {
+                    name: name.clone(),
+                    timetable_id: entry.id,
+                }),
+            }
+        }
+
+        professors.sort_by(|a, b| a.name.cmp(&b.name));
+
+        Ok(professors)
+    }
+
+    async fn get_major_ids_by_professor(
+        &self,
+        professor: &str,
+        date: NaiveDate,
+    ) -> Result<Vec<String>> {
+        let state = self.state();
+
+        let recurring = state
+            .timetable
+            .iter()
+            .filter(|entry| entry.professor.as_deref() == Some(professor))
+            .filter_map(|entry| entry.major_id.clone());
+
+        let extra = state
+            .overrides
+            .iter()
+            .filter(|entry| entry.kind == OverrideKind::Extra && entry.date == date)
+            .filter(|entry| entry.professor.as_deref() == Some(professor))
+            .filter_map(|entry| entry.major_id.clone());
+
+        let mut major_ids = recurring.chain(extra).collect::<Vec<_>>();
+        major_ids.sort();
+        major_ids.dedup();
+
+        Ok(major_ids)
+    }
+
+    async fn get_auditoriums(&self) -> Result<Vec<String>> {
+        let mut auditoriums = self
+            .state()
+            .timetable
+            .iter()
+            .map(|entry| entry.auditorium.clone())
+            .collect::<Vec<_>>();
+
+        auditoriums.sort();
+        auditoriums.dedup();
+
+        Ok(auditoriums)
+    }
+
+    async fn upsert_digest_subscription(
+        &self,
+        subscription: &DigestSubscription,
+    ) -> Result<DigestSubscription> {
+        let mut state = self.state();
+
+        state
+            .digests
+            .retain(|digest| digest.user_id != subscription.user_id);
+        state.digests.push(subscription.clone());
+
+        Ok(subscription.clone())
+    }
+
+    async fn delete_digest_subscription(&self, user_id: i64) -> Result<bool> {
+        let mut state = self.state();
+        let count = state.digests.len();
+
+        state.digests.retain(|digest| digest.user_id != user_id);
+
+        Ok(state.digests.len() < count)
+    }
+
+    async fn get_due_digests(&self, date: NaiveDate, time: NaiveTime) -> Result<Vec<DueDigest>> {
+        let state = self.state();
+
+        let due = state
+            .digests
+            .iter()
+            .filter(|digest| digest.send_at <= time)
+            .filter(|digest| digest.last_sent_on.map_or(true, |sent_on| sent_on < date))
+            .filter_map(|digest| {
+                let user = state.users.get(&digest.user_id)?;
+
+                Some(DueDigest {
+                    user_id: digest.user_id,
+                    major_id: user.major_id.clone()?,
+                    subgroup: user.subgroup.clone(),
+                    language: user.language,
+                    next_day: digest.next_day,
+                })
+            })
+            .collect();
+
+        Ok(due)
+    }
+
+    async fn mark_digest_sent(&self, user_id: i64, date: NaiveDate) -> Result<()> {
+        for digest in self.state().digests.iter_mut() {
+            if digest.user_id == user_id {
+                digest.last_sent_on = Some(date);
+            }
+        }
+
+        Ok(())
+    }
+
+    async fn upsert_reminder_setting(&self, user_id: i64, lead_minutes: i16) -> Result<()> {
+        self.state().reminders.insert(user_id, lead_minutes);
+        Ok(())
+    }
+
+    async fn delete_reminder_setting(&self, user_id: i64) -> Result<bool> {
+        Ok(self.state().reminders.remove(&user_id).is_some())
+    }
+
+    async fn get_reminder_settings(&self) -> Result<Vec<ReminderSetting>> {
+        let state = self.state();
+
+        let settings = state
+            .reminders
+            .iter()
+            .filter_map(|(user_id, lead_minutes)| {
+                let user = state.users.get(user_id)?;
+
+                Some(ReminderSetting {
+                    user_id: *user_id,
+                    major_id: user.major_id.clone()?,
+                    subgroup: user.subgroup.clone(),
+                    language: user.language,
+                    lead_minutes: *lead_minutes,
+                })
+            })
+            .collect();
+
+        Ok(settings)
+    }
+
+    async fn get_reminded_electives(&self) -> Result<Vec<ElectiveEntry>> {
+        let state = self.state();
+
+        Ok(state
+            .electives
+            .iter()
+            .filter(|elective| state.reminders.contains_key(&elective.user_id))
+            .cloned()
+            .collect())
+    }
+
+    async fn mark_reminder_sent(
+        &self,
+        user_id: i64,
+        lesson_date: NaiveDate,
+        starts_at: NaiveTime,
+        lesson_id: LessonId,
+    ) -> Result<bool> {
+        Ok(self
+            .state()
+            .sent_reminders
+            .insert((user_id, lesson_date, starts_at, lesson_id)))
+    }
+
+    async fn delete_sent_reminders_before(&self, lesson_date: NaiveDate) -> Result<()> {
+        self.state()
+            .sent_reminders
+            .retain(|(_, date, _, _)| *date >= lesson_date);
+
+        Ok(())
+    }
+}
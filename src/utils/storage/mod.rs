@@ -0,0 +1,214 @@
+#[cfg(test)]
+pub mod fixtures;
+#[cfg(test)]
+pub mod memory;
+pub mod postgres;
+
+use anyhow::Result;
+use async_trait::async_trait;
+use chrono::{NaiveDate, NaiveTime};
+
+use super::{
+    i18n::Language,
+    import::TimetableDiff,
+    sql::types::{
+        DayOfWeek, DigestSubscription, DueDigest, ElectiveEntry, LessonDurationEntry, LessonId,
+        MajorEntry, NewTimeTableEntry, OverrideEntry, ProfessorEntry, ReminderSetting,
+        SemesterEntry, SubgroupEntry, TimeTableEntry, TimeTableKey, UserEntry, WeekType,
+    },
+};
+
+/// Majors, users and timetables as the handlers and background tasks see them.
+///
+/// Handlers get it as `Arc<dyn Storage>` from the dispatcher, so that they can be tested
+/// with the in-memory implementation. Changes which take several queries are a single
+/// method, so that `PgStorage` can run them in a transaction.
+#[async_trait]
+pub trait Storage: Send + Sync {
+    async fn get_major(&self, id: &str) -> Result<Option<MajorEntry>>;
+
+    /// All majors ordered by their id.
+    async fn get_majors(&self) -> Result<Vec<MajorEntry>>;
+
+    /// The user, `None` if they have not chosen a major yet.
+    async fn get_user(&self, id: i64) -> Result<Option<UserEntry>>;
+
+    /// Creates the user if needed, the subgroup belongs to the previous major so it is reset.
+    async fn set_user_major(&self, id: i64, major_id: &str) -> Result<UserEntry>;
+
+    /// Language of the user, the default one if the user is unknown.
+    async fn get_user_language(&self, id: i64) -> Result<Language>;
+
+    async fn set_user_language(&self, id: i64, language: Language) -> Result<()>;
+
+    async fn set_user_subgroup(&self, id: i64, subgroup: Option<&str>) -> Result<()>;
+
+    /// Ids of users of the major, or of every user if `major_id` is `None`.
+    async fn get_user_ids_by_major(&self, major_id: Option<&str>) -> Result<Vec<i64>>;
+
+    /// Major of the user, or the default major of the chat if the user has not set one.
+    async fn get_major_id_for(&self, user_id: i64, chat_id: Option<i64>) -> Result<Option<String>>;
+
+    async fn set_chat_major(&self, chat_id: i64, major_id: &str) -> Result<()>;
+
+    async fn get_subgroups(&self, major_id: &str) -> Result<Vec<SubgroupEntry>>;
+
+    /// Subgroups of all majors ordered by the major and the name.
+    async fn get_all_subgroups(&self) -> Result<Vec<SubgroupEntry>>;
+
+    /// Returns `false` if the major already has such a subgroup.
+    async fn insert_subgroup(&self, major_id: &str, name: &str) -> Result<bool>;
+
+    /// Deletes the subgroup together with its lessons, members keep the major.
+    async fn delete_subgroup(&self, major_id: &str, name: &str) -> Result<bool>;
+
+    async fn get_electives(&self, user_id: i64) -> Result<Vec<ElectiveEntry>>;
+
+    /// Returns `false` if the user already attends the subject.
+    async fn insert_elective(
+        &self,
+        user_id: i64,
+        major_id: &str,
+        subject_name: &str,
+    ) -> Result<bool>;
+
+    async fn delete_elective(
+        &self,
+        user_id: i64,
+        major_id: &str,
+        subject_name: &str,
+    ) -> Result<bool>;
+
+    async fn get_timetable_by_major(&self, major_id: &str) -> Result<Vec<TimeTableEntry>>;
+
+    /// Recurring lessons of the major on the day, ordered by their start.
+    async fn get_timetable_for_day(
+        &self,
+        major_id: &str,
+        week: WeekType,
+        day_of_week: DayOfWeek,
+    ) -> Result<Vec<TimeTableEntry>>;
+
+    /// Recurring lessons of the majors on the day of both week types, ordered by their start.
+    async fn get_timetables_for_day(
+        &self,
+        major_ids: &[String],
+        day_of_week: DayOfWeek,
+    ) -> Result<Vec<TimeTableEntry>>;
+
+    async fn get_timetable_entries_by_ids(&self, ids: &[i64]) -> Result<Vec<TimeTableEntry>>;
+
+    async fn get_timetable_entry_by_key(
+        &self,
+        key: &TimeTableKey,
+    ) -> Result<Option<TimeTableEntry>>;
+
+    async fn insert_timetable_entry(&self, entry: &NewTimeTableEntry) -> Result<TimeTableEntry>;
+
+    /// Changes the lesson in the slot of the entry, returns the lesson before and after the change.
+    async fn update_timetable_entry(
+        &self,
+        entry: &NewTimeTableEntry,
+    ) -> Result<Option<(TimeTableEntry, TimeTableEntry)>>;
+
+    async fn delete_timetable_entry(&self, key: &TimeTableKey) -> Result<Option<TimeTableEntry>>;
+
+    /// Replaces the timetable of the major with the imported one at once,
+    /// the diff is computed against the timetable at the moment of the import.
+    async fn import_timetable(
+        &self,
+        major_id: &str,
+        entries: Vec<NewTimeTableEntry>,
+    ) -> Result<TimetableDiff>;
+
+    /// One lesson of every subject of the major, so that the subject can be referred to by its id.
+    async fn get_subjects_by_major(&self, major_id: &str) -> Result<Vec<TimeTableEntry>>;
+
+    /// Semesters of the major together with the global ones.
+    async fn get_semesters_for_major(&self, major_id: &str) -> Result<Vec<SemesterEntry>>;
+
+    /// Semesters of all majors ordered by their start, global ones first.
+    async fn get_all_semesters(&self) -> Result<Vec<SemesterEntry>>;
+
+    async fn insert_semester(
+        &self,
+        major_id: Option<&str>,
+        starts_on: NaiveDate,
+        ends_on: NaiveDate,
+        first_week: WeekType,
+    ) -> Result<SemesterEntry>;
+
+    async fn delete_semester(&self, id: i64) -> Result<bool>;
+
+    async fn get_lesson_durations(&self) -> Result<Vec<LessonDurationEntry>>;
+
+    async fn upsert_lesson_duration(
+        &self,
+        major_id: Option<&str>,
+        subject_type: Option<&str>,
+        duration_minutes: i16,
+    ) -> Result<LessonDurationEntry>;
+
+    async fn delete_lesson_duration(&self, id: i64) -> Result<bool>;
+
+    /// Overrides which change the timetable of the major on the date, including global holidays.
+    async fn get_overrides_for_date(
+        &self,
+        major_id: &str,
+        date: NaiveDate,
+    ) -> Result<Vec<OverrideEntry>>;
+
+    /// Overrides of all majors which change the timetable on the date.
+    async fn get_all_overrides_for_date(&self, date: NaiveDate) -> Result<Vec<OverrideEntry>>;
+
+    /// Overrides which change the timetable on the date or later.
+    async fn get_overrides_since(&self, date: NaiveDate) -> Result<Vec<OverrideEntry>>;
+
+    /// Stores an override, the `id` of the given entry is ignored.
+    async fn insert_override(&self, entry: &OverrideEntry) -> Result<OverrideEntry>;
+
+    async fn delete_override(&self, id: i64) -> Result<Option<OverrideEntry>>;
+
+    async fn get_professors(&self) -> Result<Vec<ProfessorEntry>>;
+
+    /// Majors which the professor teaches, including one-off lessons on the date.
+    async fn get_major_ids_by_professor(
+        &self,
+        professor: &str,
+        date: NaiveDate,
+    ) -> Result<Vec<String>>;
+
+    async fn get_auditoriums(&self) -> Result<Vec<String>>;
+
+    async fn upsert_digest_subscription(
+        &self,
+        subscription: &DigestSubscription,
+    ) -> Result<DigestSubscription>;
+
+    async fn delete_digest_subscription(&self, user_id: i64) -> Result<bool>;
+
+    /// Subscriptions which were not sent on `date` and whose time has come.
+    async fn get_due_digests(&self, date: NaiveDate, time: NaiveTime) -> Result<Vec<DueDigest>>;
+
+    async fn mark_digest_sent(&self, user_id: i64, date: NaiveDate) -> Result<()>;
+
+    async fn upsert_reminder_setting(&self, user_id: i64, lead_minutes: i16) -> Result<()>;
+
+    async fn delete_reminder_setting(&self, user_id: i64) -> Result<bool>;
+
+    async fn get_reminder_settings(&self) -> Result<Vec<ReminderSetting>>;
+
+    /// Electives of the users who are reminded about lessons.
+    async fn get_reminded_electives(&self) -> Result<Vec<ElectiveEntry>>;
+
+    /// Remembers that a reminder was sent, returns `false` if it had already been sent.
+    async fn mark_reminder_sent(
+        &self,
+        user_id: i64,
+        lesson_date: NaiveDate,
+        starts_at: NaiveTime,
+        lesson_id: LessonId,
+    ) -> Result<bool>;
+
+    async fn delete_sent_reminders_before(&self, lesson_date: NaiveDate) -> Result<()>;
+}
@@ -0,0 +1,345 @@
+use anyhow::Result;
+use async_trait::async_trait;
+use chrono::{NaiveDate, NaiveTime};
+
+use crate::utils::{
+    database::Database,
+    i18n::Language,
+    import::TimetableDiff,
+    sql::{
+        models,
+        types::{
+            DayOfWeek, DigestSubscription, DueDigest, ElectiveEntry, LessonDurationEntry, LessonId,
+            MajorEntry, NewTimeTableEntry, OverrideEntry, ProfessorEntry, ReminderSetting,
+            SemesterEntry, SubgroupEntry, TimeTableEntry, TimeTableKey, UserEntry, WeekType,
+        },
+    },
+};
+
+use super::Storage;
+
+/// The storage backed by the bot's database, queries live in `sql::models`.
+#[derive(Debug, Clone)]
+pub struct PgStorage {
+    db: Database,
+}
+
+impl PgStorage {
+    pub fn new(db: Database) -> Self {
+        Self { db }
+    }
+}
+
+#[async_trait]
+impl Storage for PgStorage {
+    async fn get_major(&self, id: &str) -> Result<Option<MajorEntry>> {
+        models::get_major_by_id_opt(self.db.pool.as_ref(), id).await
+    }
+
+    async fn get_majors(&self) -> Result<Vec<MajorEntry>> {
+        models::get_majors(self.db.pool.as_ref()).await
+    }
+
+    async fn get_user(&self, id: i64) -> Result<Option<UserEntry>> {
+        models::get_user_by_id_opt(self.db.pool.as_ref(), id).await
+    }
+
+    async fn set_user_major(&self, id: i64, major_id: &str) -> Result<UserEntry> {
+        models::set_user_major(self.db.pool.as_ref(), id, major_id).await
+    }
+
+    async fn get_user_language(&self, id: i64) -> Result<Language> {
+        models::get_user_language(self.db.pool.as_ref(), id).await
+    }
+
+    async fn set_user_language(&self, id: i64, language: Language) -> Result<()> {
+        models::set_user_language(self.db.pool.as_ref(), id, language).await
+    }
+
+    async fn set_user_subgroup(&self, id: i64, subgroup: Option<&str>) -> Result<()> {
+        models::set_user_subgroup(self.db.pool.as_ref(), id, subgroup).await
+    }
+
+    async fn get_user_ids_by_major(&self, major_id: Option<&str>) -> Result<Vec<i64>> {
+        models::get_user_ids_by_major(self.db.pool.as_ref(), major_id).await
+    }
+
+    async fn get_major_id_for(&self, user_id: i64, chat_id: Option<i64>) -> Result<Option<String>> {
+        models::get_major_id_for(self.db.pool.as_ref(), user_id, chat_id).await
+    }
+
+    async fn set_chat_major(&self, chat_id: i64, major_id: &str) -> Result<()> {
+        models::set_chat_major(self.db.pool.as_ref(), chat_id, major_id).await
+    }
+
+    async fn get_subgroups(&self, major_id: &str) -> Result<Vec<SubgroupEntry>> {
+        models::get_subgroups(self.db.pool.as_ref(), major_id).await
+    }
+
+    async fn get_all_subgroups(&self) -> Result<Vec<SubgroupEntry>> {
+        models::get_all_subgroups(self.db.pool.as_ref()).await
+    }
+
+    async fn insert_subgroup(&self, major_id: &str, name: &str) -> Result<bool> {
+        models::insert_subgroup(self.db.pool.as_ref(), major_id, name).await
+    }
+
+    async fn delete_subgroup(&self, major_id: &str, name: &str) -> Result<bool> {
+        models::delete_subgroup(self.db.pool.as_ref(), major_id, name).await
+    }
+
+    async fn get_electives(&self, user_id: i64) -> Result<Vec<ElectiveEntry>> {
+        models::get_electives(self.db.pool.as_ref(), user_id).await
+    }
+
+    async fn insert_elective(
+        &self,
+        user_id: i64,
+        major_id: &str,
+        subject_name: &str,
+    ) -> Result<bool> {
+        models::insert_elective(self.db.pool.as_ref(), user_id, major_id, subject_name).await
+    }
+
+    async fn delete_elective(
+        &self,
+        user_id: i64,
+        major_id: &str,
+        subject_name: &str,
+    ) -> Result<bool> {
+        models::delete_elective(self.db.pool.as_ref(), user_id, major_id, subject_name).await
+    }
+
+    async fn get_timetable_by_major(&self, major_id: &str) -> Result<Vec<TimeTableEntry>> {
+        models::get_timetable_by_major(self.db.pool.as_ref(), major_id).await
+    }
+
+    async fn get_timetable_for_day(
+        &self,
+        major_id: &str,
+        week: WeekType,
+        day_of_week: DayOfWeek,
+    ) -> Result<Vec<TimeTableEntry>> {
+        models::get_timetable_for_day(self.db.pool.as_ref(), major_id, week, day_of_week).await
+    }
+
+    async fn get_timetables_for_day(
+        &self,
+        major_ids: &[String],
+        day_of_week: DayOfWeek,
+    ) -> Result<Vec<TimeTableEntry>> {
+        models::get_timetables_for_day(self.db.pool.as_ref(), major_ids, day_of_week).await
+    }
+
+    async fn get_timetable_entries_by_ids(&self, ids: &[i64]) -> Result<Vec<TimeTableEntry>> {
+        models::get_timetable_entries_by_ids(self.db.pool.as_ref(), ids).await
+    }
+
+    async fn get_timetable_entry_by_key(
+        &self,
+        key: &TimeTableKey,
+    ) -> Result<Option<TimeTableEntry>> {
+        models::get_timetable_entry_by_key(self.db.pool.as_ref(), key).await
+    }
+
+    async fn insert_timetable_entry(&self, entry: &NewTimeTableEntry) -> Result<TimeTableEntry> {
+        models::insert_timetable_entry(self.db.pool.as_ref(), entry).await
+    }
+
+    async fn update_timetable_entry(
+        &self,
+        entry: &NewTimeTableEntry,
+    ) -> Result<Option<(TimeTableEntry, TimeTableEntry)>> {
+        let mut tx = self.db.pool.begin().await?;
+
+        let old = models::get_timetable_entry_by_key(&mut *tx, &entry.key).await?;
+        let new = models::update_timetable_entry(&mut *tx, entry).await?;
+
+        tx.commit().await?;
+
+        Ok(old.zip(new))
+    }
+
+    async fn delete_timetable_entry(&self, key: &TimeTableKey) -> Result<Option<TimeTableEntry>> {
+        models::delete_timetable_entry(self.db.pool.as_ref(), key).await
+    }
+
+    async fn import_timetable(
+        &self,
+        major_id: &str,
+        entries: Vec<NewTimeTableEntry>,
+    ) -> Result<TimetableDiff> {
+        let mut tx = self.db.pool.begin().await?;
+
+        let existing = models::get_timetable_by_major(&mut *tx, major_id).await?;
+        let diff = TimetableDiff::new(existing, entries);
+
+        for entry in &diff.removed {
+            models::delete_timetable_entry(&mut *tx, &entry.key()).await?;
+        }
+
+        for (_, entry) in &diff.changed {
+            models::update_timetable_entry(&mut *tx, entry).await?;
+        }
+
+        for entry in &diff.added {
+            models::insert_timetable_entry(&mut *tx, entry).await?;
+        }
+
+        tx.commit().await?;
+
+        Ok(diff)
+    }
+
+    async fn get_subjects_by_major(&self, major_id: &str) -> Result<Vec<TimeTableEntry>> {
+        models::get_subjects_by_major(self.db.pool.as_ref(), major_id).await
+    }
+
+    async fn get_semesters_for_major(&self, major_id: &str) -> Result<Vec<SemesterEntry>> {
+        models::get_semesters_for_major(self.db.pool.as_ref(), major_id).await
+    }
+
+    async fn get_all_semesters(&self) -> Result<Vec<SemesterEntry>> {
+        models::get_all_semesters(self.db.pool.as_ref()).await
+    }
+
+    async fn insert_semester(
+        &self,
+        major_id: Option<&str>,
+        starts_on: NaiveDate,
+        ends_on: NaiveDate,
+        first_week: WeekType,
+    ) -> Result<SemesterEntry> {
+        models::insert_semester(
+            self.db.pool.as_ref(),
+            major_id,
+            starts_on,
+            ends_on,
+            first_week,
+        )
+        .await
+    }
+
+    async fn delete_semester(&self, id: i64) -> Result<bool> {
+        models::delete_semester(self.db.pool.as_ref(), id).await
+    }
+
+    async fn get_lesson_durations(&self) -> Result<Vec<LessonDurationEntry>> {
+        models::get_lesson_durations(self.db.pool.as_ref()).await
+    }
+
+    async fn upsert_lesson_duration(
+        &self,
+        major_id: Option<&str>,
+        subject_type: Option<&str>,
+        duration_minutes: i16,
+    ) -> Result<LessonDurationEntry> {
+        models::upsert_lesson_duration(
+            self.db.pool.as_ref(),
+            major_id,
+            subject_type,
+            duration_minutes,
+        )
+        .await
+    }
+
+    async fn delete_lesson_duration(&self, id: i64) -> Result<bool> {
+        models::delete_lesson_duration(self.db.pool.as_ref(), id).await
+    }
+
+    async fn get_overrides_for_date(
+        &self,
+        major_id: &str,
+        date: NaiveDate,
+    ) -> Result<Vec<OverrideEntry>> {
+        models::get_overrides_for_date(self.db.pool.as_ref(), major_id, date).await
+    }
+
+    async fn get_all_overrides_for_date(&self, date: NaiveDate) -> Result<Vec<OverrideEntry>> {
+        models::get_all_overrides_for_date(self.db.pool.as_ref(), date).await
+    }
+
+    async fn get_overrides_since(&self, date: NaiveDate) -> Result<Vec<OverrideEntry>> {
+        models::get_overrides_since(self.db.pool.as_ref(), date).await
+    }
+
+    async fn insert_override(&self, entry: &OverrideEntry) -> Result<OverrideEntry> {
+        models::insert_override(self.db.pool.as_ref(), entry).await
+    }
+
+    async fn delete_override(&self, id: i64) -> Result<Option<OverrideEntry>> {
+        models::delete_override(self.db.pool.as_ref(), id).await
+    }
+
+    async fn get_professors(&self) -> Result<Vec<ProfessorEntry>> {
+        models::get_professors(self.db.pool.as_ref()).await
+    }
+
+    async fn get_major_ids_by_professor(
+        &self,
+        professor: &str,
+        date: NaiveDate,
+    ) -> Result<Vec<String>> {
+        models::get_major_ids_by_professor(self.db.pool.as_ref(), professor, date).await
+    }
+
+    async fn get_auditoriums(&self) -> Result<Vec<String>> {
+        models::get_auditoriums(self.db.pool.as_ref()).await
+    }
+
+    async fn upsert_digest_subscription(
+        &self,
+        subscription: &DigestSubscription,
+    ) -> Result<DigestSubscription> {
+        models::upsert_digest_subscription(self.db.pool.as_ref(), subscription).await
+    }
+
+    async fn delete_digest_subscription(&self, user_id: i64) -> Result<bool> {
+        models::delete_digest_subscription(self.db.pool.as_ref(), user_id).await
+    }
+
+    async fn get_due_digests(&self, date: NaiveDate, time: NaiveTime) -> Result<Vec<DueDigest>> {
+        models::get_due_digests(self.db.pool.as_ref(), date, time).await
+    }
+
+    async fn mark_digest_sent(&self, user_id: i64, date: NaiveDate) -> Result<()> {
+        models::mark_digest_sent(self.db.pool.as_ref(), user_id, date).await
+    }
+
+    async fn upsert_reminder_setting(&self, user_id: i64, lead_minutes: i16) -> Result<()> {
+        models::upsert_reminder_setting(self.db.pool.as_ref(), user_id, lead_minutes).await
+    }
+
+    async fn delete_reminder_setting(&self, user_id: i64) -> Result<bool> {
+        models::delete_reminder_setting(self.db.pool.as_ref(), user_id).await
+    }
+
+    async fn get_reminder_settings(&self) -> Result<Vec<ReminderSetting>> {
+        models::get_reminder_settings(self.db.pool.as_ref()).await
+    }
+
+    async fn get_reminded_electives(&self) -> Result<Vec<ElectiveEntry>> {
+        models::get_reminded_electives(self.db.pool.as_ref()).await
+    }
+
+    async fn mark_reminder_sent(
+        &self,
+        user_id: i64,
+        lesson_date: NaiveDate,
+        starts_at: NaiveTime,
+        lesson_id: LessonId,
+    ) -> Result<bool> {
+        models::mark_reminder_sent(
+            self.db.pool.as_ref(),
+            user_id,
+            lesson_date,
+            starts_at,
+            lesson_id,
+        )
+        .await
+    }
+
+    async fn delete_sent_reminders_before(&self, lesson_date: NaiveDate) -> Result<()> {
+        models::delete_sent_reminders_before(self.db.pool.as_ref(), lesson_date).await
+    }
+}
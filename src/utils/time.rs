@@ -1,13 +1,317 @@
 use anyhow::{anyhow, Result};
-use chrono::{DateTime, FixedOffset, Utc};
+use chrono::{
+    DateTime, Datelike, Duration, FixedOffset, IsoWeek, NaiveDate, NaiveTime, Offset, TimeZone,
+    Utc, Weekday,
+};
+use chrono_tz::Tz;
+use once_cell::sync::OnceCell;
 
-pub const TIME_OFFSET_SECONDS: i32 = 3600 * 4;
+/// Timezone of the institution if the config does not specify one.
+pub const DEFAULT_TIMEZONE: Tz = chrono_tz::Europe::Samara;
 
-pub fn global_offset() -> Result<FixedOffset> {
-    FixedOffset::east_opt(TIME_OFFSET_SECONDS).ok_or(anyhow!("seconds are out of bounds"))
+/// Hour of the day carried by buttons which refer to a whole day.
+const DAY_BUTTON_HOUR: u32 = 8;
+
+static TIMEZONE: OnceCell<Tz> = OnceCell::new();
+
+/// Sets the timezone lessons are scheduled in, must be called once on startup.
+pub fn init_timezone(tz: Tz) -> Result<()> {
+    TIMEZONE
+        .set(tz)
+        .map_err(|_| anyhow!("the timezone is already set"))
+}
+
+pub fn timezone() -> Tz {
+    TIMEZONE.get().copied().unwrap_or(DEFAULT_TIMEZONE)
+}
+
+/// The instant in the institution timezone with the offset which is in effect at that instant.
+pub fn localize<T: TimeZone>(dt: &DateTime<T>) -> DateTime<FixedOffset> {
+    localize_in(timezone(), dt)
+}
+
+fn localize_in<T: TimeZone>(tz: Tz, dt: &DateTime<T>) -> DateTime<FixedOffset> {
+    let local = dt.with_timezone(&tz);
+    local.with_timezone(&local.offset().fix())
 }
 
 pub fn now() -> Result<DateTime<FixedOffset>> {
-    let offset = global_offset()?;
-    Ok(Utc::now().with_timezone(&offset))
+    Ok(localize(&Utc::now()))
+}
+
+/// The local time on the date in the institution timezone.
+///
+/// The earlier instant is picked if the time is ambiguous, a time skipped by a DST
+/// transition is moved forward by the length of the gap.
+pub fn local_datetime(date: NaiveDate, time: NaiveTime) -> Result<DateTime<FixedOffset>> {
+    local_datetime_in(timezone(), date, time)
+}
+
+fn local_datetime_in(tz: Tz, date: NaiveDate, time: NaiveTime) -> Result<DateTime<FixedOffset>> {
+    let naive = date.and_time(time);
+
+    tz.from_local_datetime(&naive)
+        .earliest()
+        .or_else(|| {
+            tz.from_local_datetime(&(naive + Duration::hours(1)))
+                .earliest()
+        })
+        .map(|dt| localize_in(tz, &dt))
+        .ok_or(anyhow!("{naive} does not exist in {tz}"))
+}
+
+/// The moment which represents the whole date on day buttons.
+pub fn day_start(date: NaiveDate) -> Result<DateTime<FixedOffset>> {
+    day_start_in(timezone(), date)
+}
+
+fn day_start_in(tz: Tz, date: NaiveDate) -> Result<DateTime<FixedOffset>> {
+    let time = NaiveTime::from_hms_opt(DAY_BUTTON_HOUR, 0, 0).ok_or(anyhow!("invalid hour"))?;
+    local_datetime_in(tz, date, time)
+}
+
+/// Moves `dt` by whole days keeping its local time, unlike adding 24 hour periods
+/// this stays correct across DST transitions.
+pub fn add_days(dt: &DateTime<FixedOffset>, days: i64) -> Result<DateTime<FixedOffset>> {
+    add_days_in(timezone(), dt, days)
+}
+
+fn add_days_in(tz: Tz, dt: &DateTime<FixedOffset>, days: i64) -> Result<DateTime<FixedOffset>> {
+    local_datetime_in(tz, dt.date_naive() + Duration::days(days), dt.time())
+}
+
+/// An ISO week, from Monday to Sunday.
+///
+/// Weeks are identified by their Monday, so moving between them works across the years,
+/// including the ISO years with 53 weeks and the days of January which belong to the
+/// last week of the previous year.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Week {
+    monday: NaiveDate,
+}
+
+impl Week {
+    /// The week the date belongs to.
+    pub fn containing(date: NaiveDate) -> Self {
+        let monday = date - Duration::days(date.weekday().num_days_from_monday().into());
+        Self { monday }
+    }
+
+    /// The week by its ISO year and number, `None` if the year has no such week.
+    pub fn from_iso(year: i32, week: u32) -> Option<Self> {
+        NaiveDate::from_isoywd_opt(year, week, Weekday::Mon).map(|monday| Self { monday })
+    }
+
+    pub fn monday(&self) -> NaiveDate {
+        self.monday
+    }
+
+    pub fn sunday(&self) -> NaiveDate {
+        self.monday + Duration::days(6)
+    }
+
+    pub fn iso_week(&self) -> IsoWeek {
+        self.monday.iso_week()
+    }
+
+    /// The week `weeks` weeks later, or earlier if `weeks` is negative.
+    pub fn offset(&self, weeks: i64) -> Self {
+        Self {
+            monday: self.monday + Duration::weeks(weeks),
+        }
+    }
+
+    /// Days of the week from Monday to Sunday.
+    pub fn days(&self) -> impl Iterator<Item = NaiveDate> {
+        let monday = self.monday;
+        (0..7).map(move |i| monday + Duration::days(i))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::storage::fixtures::date;
+
+    #[test]
+    fn week_starts_on_monday() {
+        let week = Week::containing(date(2023, 3, 16));
+
+        assert_eq!(week.monday(), date(2023, 3, 13));
+        assert_eq!(week.sunday(), date(2023, 3, 19));
+        assert_eq!(Week::containing(date(2023, 3, 13)), week);
+        assert_eq!(Week::containing(date(2023, 3, 19)), week);
+    }
+
+    #[test]
+    fn days_go_from_monday_to_sunday() {
+        let days = Week::containing(date(2023, 3, 16))
+            .days()
+            .collect::<Vec<_>>();
+
+        assert_eq!(days.len(), 7);
+        assert_eq!(days[0], date(2023, 3, 13));
+        assert_eq!(days[6], date(2023, 3, 19));
+        assert!(days
+            .windows(2)
+            .all(|pair| pair[1] - pair[0] == Duration::days(1)));
+    }
+
+    #[test]
+    fn january_days_may_belong_to_the_previous_iso_year() {
+        let week = Week::containing(date(2021, 1, 1));
+
+        assert_eq!(week.monday(), date(2020, 12, 28));
+        assert_eq!(week.iso_week().year(), 2020);
+        assert_eq!(week.iso_week().week(), 53);
+        assert_eq!(week.sunday(), date(2021, 1, 3));
+    }
+
+    #[test]
+    fn december_days_may_belong_to_the_next_iso_year() {
+        let week = Week::containing(date(2024, 12, 31));
+
+        assert_eq!(week.monday(), date(2024, 12, 30));
+        assert_eq!(week.iso_week().year(), 2025);
+        assert_eq!(week.iso_week().week(), 1);
+    }
+
+    #[test]
+    fn next_week_crosses_the_year_boundary() {
+        let last = Week::from_iso(2023, 52).unwrap();
+        let next = last.offset(1);
+
+        assert_eq!(next.monday(), date(2024, 1, 1));
+        assert_eq!(next.iso_week().year(), 2024);
+        assert_eq!(next.iso_week().week(), 1);
+    }
+
+    #[test]
+    fn previous_week_crosses_the_year_boundary() {
+        let first = Week::from_iso(2025, 1).unwrap();
+        let previous = first.offset(-1);
+
+        assert_eq!(previous.monday(), date(2024, 12, 23));
+        assert_eq!(previous.iso_week().year(), 2024);
+        assert_eq!(previous.iso_week().week(), 52);
+    }
+
+    #[test]
+    fn years_with_53_weeks() {
+        for year in [2015, 2020, 2026] {
+            let last = Week::from_iso(year, 53).unwrap();
+
+            assert_eq!(last.offset(-1), Week::from_iso(year, 52).unwrap());
+            assert_eq!(last.offset(1), Week::from_iso(year + 1, 1).unwrap());
+        }
+
+        assert_eq!(
+            Week::from_iso(2020, 53).unwrap().monday(),
+            date(2020, 12, 28)
+        );
+        assert_eq!(
+            Week::from_iso(2026, 52)
+                .unwrap()
+                .offset(1)
+                .iso_week()
+                .week(),
+            53
+        );
+    }
+
+    #[test]
+    fn years_with_52_weeks_have_no_53rd_week() {
+        for year in [2021, 2023, 2024, 2025] {
+            assert!(Week::from_iso(year, 53).is_none());
+            assert_eq!(
+                Week::from_iso(year, 52).unwrap().offset(1),
+                Week::from_iso(year + 1, 1).unwrap()
+            );
+        }
+    }
+
+    #[test]
+    fn offsets_span_several_years() {
+        let week = Week::from_iso(2020, 10).unwrap();
+
+        // 2020 has 53 weeks, 2021 has 52
+        assert_eq!(week.offset(53 + 52), Week::from_iso(2022, 10).unwrap());
+        assert_eq!(week.offset(53 + 52).offset(-(53 + 52)), week);
+    }
+
+    const BERLIN: Tz = chrono_tz::Europe::Berlin;
+
+    fn berlin(date: NaiveDate, hour: u32, minute: u32) -> DateTime<FixedOffset> {
+        let time = NaiveTime::from_hms_opt(hour, minute, 0).unwrap();
+        local_datetime_in(BERLIN, date, time).unwrap()
+    }
+
+    fn utc(dt: &DateTime<FixedOffset>) -> String {
+        dt.with_timezone(&Utc).format("%Y-%m-%d %H:%M").to_string()
+    }
+
+    #[test]
+    fn skipped_time_is_moved_forward() {
+        // on 2024-03-31 clocks jump from 02:00 CET to 03:00 CEST
+        let dt = berlin(date(2024, 3, 31), 2, 30);
+
+        assert_eq!(dt.to_rfc3339(), "2024-03-31T03:30:00+02:00");
+        assert_eq!(
+            berlin(date(2024, 3, 31), 1, 30).to_rfc3339(),
+            "2024-03-31T01:30:00+01:00"
+        );
+    }
+
+    #[test]
+    fn ambiguous_time_is_the_earlier_instant() {
+        // on 2024-10-27 clocks go back from 03:00 CEST to 02:00 CET, 02:30 happens twice
+        let dt = berlin(date(2024, 10, 27), 2, 30);
+
+        assert_eq!(dt.to_rfc3339(), "2024-10-27T02:30:00+02:00");
+        assert_eq!(utc(&dt), "2024-10-27 00:30");
+    }
+
+    #[test]
+    fn day_buttons_stay_on_their_date_across_dst() {
+        for (day, offset) in [
+            (date(2024, 3, 30), "+01:00"),
+            (date(2024, 3, 31), "+02:00"),
+            (date(2024, 10, 26), "+02:00"),
+            (date(2024, 10, 27), "+01:00"),
+        ] {
+            let dt = day_start_in(BERLIN, day).unwrap();
+
+            assert_eq!(dt.date_naive(), day);
+            assert_eq!(dt.to_rfc3339(), format!("{day}T08:00:00{offset}"));
+        }
+    }
+
+    #[test]
+    fn whole_days_are_added_across_dst() {
+        // spring forward, the day is 23 hours long
+        let saturday = day_start_in(BERLIN, date(2024, 3, 30)).unwrap();
+        let sunday = add_days_in(BERLIN, &saturday, 1).unwrap();
+        assert_eq!(sunday, day_start_in(BERLIN, date(2024, 3, 31)).unwrap());
+        assert_eq!(sunday - saturday, Duration::hours(23));
+
+        // fall back, the day is 25 hours long
+        let saturday = day_start_in(BERLIN, date(2024, 10, 26)).unwrap();
+        let sunday = add_days_in(BERLIN, &saturday, 1).unwrap();
+        assert_eq!(sunday, day_start_in(BERLIN, date(2024, 10, 27)).unwrap());
+        assert_eq!(sunday - saturday, Duration::hours(25));
+
+        // late in the evening, like `now()` for /tomorrow, the next date is kept
+        for (from, to) in [
+            (berlin(date(2024, 3, 30), 23, 30), date(2024, 3, 31)),
+            (berlin(date(2024, 3, 31), 23, 30), date(2024, 4, 1)),
+            (berlin(date(2024, 10, 26), 23, 30), date(2024, 10, 27)),
+            (berlin(date(2024, 10, 27), 0, 30), date(2024, 10, 28)),
+        ] {
+            let next = add_days_in(BERLIN, &from, 1).unwrap();
+
+            assert_eq!(next.date_naive(), to, "{from}");
+            assert_eq!(next.time(), from.time(), "{from}");
+            assert_eq!(add_days_in(BERLIN, &next, -1).unwrap(), from, "{from}");
+        }
+    }
 }